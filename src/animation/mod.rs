@@ -0,0 +1,466 @@
+use std::time::Duration;
+mod easing;
+mod tween;
+pub use easing::Easing;
+pub use tween::{Animatable, Property, Scene, Target, Tween, Value};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Repeat::Times(1)
+    }
+}
+
+enum Kind {
+    Tween(Tween),
+    /// Children run one after the other
+    Sequence {
+        children: Vec<Animation>,
+        current: usize,
+        /// Children are stored back to front
+        reversed: bool,
+    },
+    /// Children run at the same time, finished ones hold their leftover time
+    Parallel(Vec<(Animation, Option<Duration>)>),
+}
+
+/// A tween or a group of animations, with delay, repeats and a completion callback.
+/// Built with the constructors and chained setters, then handed to `Animator::add`.
+pub struct Animation {
+    kind: Kind,
+    delay: Duration,
+    repeat: Repeat,
+    /// Every other repeat runs backwards
+    yoyo: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+
+    waited: Duration,
+    iteration: u32,
+    /// Set by a reversed parent, flips the direction of every iteration
+    reversed: bool,
+}
+
+impl Animation {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            delay: Duration::ZERO,
+            repeat: Repeat::default(),
+            yoyo: false,
+            on_complete: None,
+            waited: Duration::ZERO,
+            iteration: 0,
+            reversed: false,
+        }
+    }
+
+    /// Tweens `property` of `target` from its current value to `to`
+    pub fn tween(
+        target: Target,
+        property: Property,
+        to: impl Into<Value>,
+        duration: Duration,
+    ) -> Self {
+        Self::new(Kind::Tween(Tween::new(
+            target,
+            property,
+            to.into(),
+            duration,
+        )))
+    }
+
+    pub fn sequence(animations: Vec<Animation>) -> Self {
+        Self::new(Kind::Sequence {
+            children: animations,
+            current: 0,
+            reversed: false,
+        })
+    }
+
+    pub fn parallel(animations: Vec<Animation>) -> Self {
+        Self::new(Kind::Parallel(
+            animations.into_iter().map(|a| (a, None)).collect(),
+        ))
+    }
+
+    /// Start value instead of the current one, only for tweens
+    pub fn from(mut self, from: impl Into<Value>) -> Self {
+        if let Kind::Tween(tween) = &mut self.kind {
+            tween.from = Some(from.into());
+        }
+        self
+    }
+
+    /// Only for tweens, groups use the easing of their children
+    pub fn easing(mut self, easing: Easing) -> Self {
+        if let Kind::Tween(tween) = &mut self.kind {
+            tween.easing = easing;
+        }
+        self
+    }
+
+    /// Waits before the first iteration, repeats start right away
+    pub fn delay(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+
+    pub fn repeat(self, repeat: Repeat) -> Self {
+        Self { repeat, ..self }
+    }
+
+    pub fn yoyo(self) -> Self {
+        Self { yoyo: true, ..self }
+    }
+
+    /// Called once after the last iteration, never for `Repeat::Forever`
+    pub fn on_complete(self, callback: impl FnMut() + 'static) -> Self {
+        Self {
+            on_complete: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    fn reset(&mut self, reversed: bool) {
+        self.waited = Duration::ZERO;
+        self.iteration = 0;
+        self.reversed = reversed;
+        self.kind.reset(reversed);
+    }
+
+    /// Time until the animation changes the scene, zero unless it waits out a delay
+    fn idle_time(&self) -> Duration {
+        self.delay.saturating_sub(self.waited) + self.kind.idle_time()
+    }
+
+    /// Returns the part of `dt` that is left once the animation finished, `None` while it runs
+    fn advance(&mut self, mut dt: Duration, scene: &mut Scene) -> Option<Duration> {
+        if self.waited < self.delay {
+            let wait = (self.delay - self.waited).min(dt);
+            self.waited += wait;
+            dt -= wait;
+            if self.waited < self.delay {
+                return None;
+            }
+        }
+
+        loop {
+            let leftover = self.kind.advance(dt, scene)?;
+            self.iteration += 1;
+
+            let finished = match self.repeat {
+                Repeat::Times(times) => self.iteration >= times,
+                Repeat::Forever => false,
+            };
+            if finished {
+                if let Some(on_complete) = &mut self.on_complete {
+                    on_complete();
+                }
+                return Some(leftover);
+            }
+
+            let reversed = self.reversed ^ (self.yoyo && self.iteration % 2 == 1);
+            self.kind.reset(reversed);
+
+            // An iteration that takes no time would repeat forever within one frame
+            if leftover == dt {
+                return None;
+            }
+            dt = leftover;
+        }
+    }
+}
+
+impl Kind {
+    fn reset(&mut self, reversed: bool) {
+        match self {
+            Kind::Tween(tween) => tween.reset(reversed),
+            Kind::Sequence {
+                children,
+                current,
+                reversed: order,
+            } => {
+                // Reversed sequences run their children back to front
+                if *order != reversed {
+                    children.reverse();
+                    *order = reversed;
+                }
+                for child in children.iter_mut() {
+                    child.reset(reversed);
+                }
+                *current = 0;
+            }
+            Kind::Parallel(children) => {
+                for (child, leftover) in children {
+                    child.reset(reversed);
+                    *leftover = None;
+                }
+            }
+        }
+    }
+
+    fn idle_time(&self) -> Duration {
+        match self {
+            Kind::Tween(_) => Duration::ZERO,
+            Kind::Sequence {
+                children, current, ..
+            } => children
+                .get(*current)
+                .map_or(Duration::ZERO, Animation::idle_time),
+            Kind::Parallel(children) => children
+                .iter()
+                .filter(|(_, leftover)| leftover.is_none())
+                .map(|(child, _)| child.idle_time())
+                .min()
+                .unwrap_or(Duration::ZERO),
+        }
+    }
+
+    fn advance(&mut self, mut dt: Duration, scene: &mut Scene) -> Option<Duration> {
+        match self {
+            Kind::Tween(tween) => tween.advance(dt, scene),
+            Kind::Sequence {
+                children, current, ..
+            } => {
+                while let Some(child) = children.get_mut(*current) {
+                    dt = child.advance(dt, scene)?;
+                    *current += 1;
+                }
+                Some(dt)
+            }
+            Kind::Parallel(children) => {
+                for (child, leftover) in children.iter_mut() {
+                    // Children that finished in an earlier frame leave all of this one over
+                    *leftover = match leftover {
+                        Some(_) => Some(dt),
+                        None => child.advance(dt, scene),
+                    };
+                }
+                // Finished when the longest child is, which has the least time left over
+                children
+                    .iter()
+                    .map(|(_, leftover)| *leftover)
+                    .try_fold(dt, |min, leftover| Some(min.min(leftover?)))
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AnimationId(u64);
+
+/// Runs animations against the scene, advanced from `State::update`
+#[derive(Default)]
+pub struct Animator {
+    animations: Vec<(AnimationId, Animation)>,
+    next_id: u64,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, animation: Animation) -> AnimationId {
+        let id = AnimationId(self.next_id);
+        self.next_id += 1;
+        self.animations.push((id, animation));
+        id
+    }
+
+    /// Stops an animation where it is, without calling its completion callback
+    pub fn cancel(&mut self, id: AnimationId) {
+        self.animations.retain(|(other, _)| *other != id);
+    }
+
+    pub fn is_running(&self, id: AnimationId) -> bool {
+        self.animations.iter().any(|(other, _)| *other == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    /// Time until the first animation changes the scene, `None` without animations. More
+    /// than zero while all of them wait out their delays.
+    pub fn idle_time(&self) -> Option<Duration> {
+        self.animations
+            .iter()
+            .map(|(_, animation)| animation.idle_time())
+            .min()
+    }
+
+    /// Advances all animations by `dt` and drops finished ones. Returns whether any ran,
+    /// in which case the scene needs to be uploaded again.
+    pub fn advance(&mut self, dt: Duration, scene: &mut Scene) -> bool {
+        if self.animations.is_empty() {
+            return false;
+        }
+
+        self.animations
+            .retain_mut(|(_, animation)| animation.advance(dt, scene).is_none());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circle::Circle;
+    use crate::rect::Rect;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Moves rect `index` to `x` on the horizontal axis only
+    fn move_x(index: usize, x: f32, millis: u64) -> Animation {
+        Animation::tween(
+            Target::Rect(index),
+            Property::Position,
+            [x, 0.0],
+            ms(millis),
+        )
+    }
+
+    fn advance(animator: &mut Animator, rects: &mut [Rect], millis: u64) {
+        let mut scene = Scene {
+            rects,
+            circles: &mut [] as &mut [Circle],
+        };
+        animator.advance(ms(millis), &mut scene);
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    /// Counts how often `on_complete` was called
+    fn counter() -> (Rc<Cell<u32>>, impl FnMut() + 'static) {
+        let count = Rc::new(Cell::new(0));
+        let inner = Rc::clone(&count);
+        (count, move || inner.set(inner.get() + 1))
+    }
+
+    #[test]
+    fn runs_sequences_one_after_the_other() {
+        let mut rects = [Rect::default()];
+        let mut animator = Animator::new();
+        let up = Animation::tween(Target::Rect(0), Property::Position, [10.0, 10.0], ms(100));
+        let id = animator.add(Animation::sequence(vec![move_x(0, 10.0, 100), up]));
+
+        advance(&mut animator, &mut rects, 50);
+        assert_eq!(rects[0].position, [5.0, 0.0]);
+        // The first tween finishes halfway through the step, the second gets the rest
+        advance(&mut animator, &mut rects, 100);
+        assert_close(rects[0].position[0], 10.0);
+        assert_close(rects[0].position[1], 5.0);
+
+        advance(&mut animator, &mut rects, 50);
+        assert_eq!(rects[0].position, [10.0, 10.0]);
+        assert!(!animator.is_running(id));
+        assert!(animator.is_empty());
+    }
+
+    #[test]
+    fn yoyos_back_to_the_start() {
+        let mut rects = [Rect::default()];
+        let (completed, on_complete) = counter();
+        let mut animator = Animator::new();
+        animator.add(
+            move_x(0, 10.0, 100)
+                .repeat(Repeat::Times(2))
+                .yoyo()
+                .on_complete(on_complete),
+        );
+
+        advance(&mut animator, &mut rects, 75);
+        assert_close(rects[0].position[0], 7.5);
+        advance(&mut animator, &mut rects, 50);
+        assert_close(rects[0].position[0], 7.5);
+        assert_eq!(completed.get(), 0);
+
+        advance(&mut animator, &mut rects, 75);
+        assert_eq!(rects[0].position[0], 0.0);
+        assert_eq!(completed.get(), 1);
+        assert!(animator.is_empty());
+    }
+
+    #[test]
+    fn counts_repeats() {
+        let mut rects = [Rect::default()];
+        let (completed, on_complete) = counter();
+        let mut animator = Animator::new();
+        animator.add(
+            move_x(0, 10.0, 100)
+                .from([0.0, 0.0])
+                .repeat(Repeat::Times(3))
+                .on_complete(on_complete),
+        );
+
+        // Two iterations and a half in one step, every one starts over from the start value
+        advance(&mut animator, &mut rects, 250);
+        assert_close(rects[0].position[0], 5.0);
+        assert!(!animator.is_empty());
+
+        advance(&mut animator, &mut rects, 50);
+        assert_eq!(rects[0].position[0], 10.0);
+        assert_eq!(completed.get(), 1);
+        assert!(animator.is_empty());
+
+        let mut animator = Animator::new();
+        let id = animator.add(move_x(0, 20.0, 100).repeat(Repeat::Forever));
+        advance(&mut animator, &mut rects, 10_000);
+        assert!(animator.is_running(id));
+        animator.cancel(id);
+        assert!(animator.is_empty());
+    }
+
+    #[test]
+    fn carries_leftover_time_across_parallel_tracks() {
+        let mut rects = [Rect::default(), Rect::default()];
+        let mut animator = Animator::new();
+        let both = Animation::parallel(vec![move_x(0, 10.0, 100), move_x(1, 10.0, 300)]);
+        let then = Animation::tween(Target::Rect(0), Property::Position, [10.0, 10.0], ms(100));
+        animator.add(Animation::sequence(vec![both, then]));
+
+        // The short track is done and holds on to its leftover time
+        advance(&mut animator, &mut rects, 150);
+        assert_eq!(rects[0].position, [10.0, 0.0]);
+        assert_close(rects[1].position[0], 5.0);
+
+        // The group ends with its longest track, whatever that leaves over goes on
+        advance(&mut animator, &mut rects, 200);
+        assert_eq!(rects[1].position[0], 10.0);
+        assert_close(rects[0].position[1], 5.0);
+    }
+
+    #[test]
+    fn waits_out_the_delay() {
+        let mut rects = [Rect::default()];
+        let mut animator = Animator::new();
+        assert_eq!(animator.idle_time(), None);
+        animator.add(move_x(0, 10.0, 100).delay(ms(100)));
+
+        assert_eq!(animator.idle_time(), Some(ms(100)));
+        advance(&mut animator, &mut rects, 40);
+        assert_eq!(animator.idle_time(), Some(ms(60)));
+        assert_eq!(rects[0].position[0], 0.0);
+
+        // The step that ends the delay already moves the rect
+        advance(&mut animator, &mut rects, 110);
+        assert_close(rects[0].position[0], 5.0);
+        assert_eq!(animator.idle_time(), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use super::Easing;
+use crate::circle::Circle;
+use crate::rect::Rect;
+
+/// Scene object an animation changes, by instance index
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Rect(usize),
+    Circle(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Property {
+    Position,
+    /// Half extent of a rect like `Rect::size`, see `Radius` for circles
+    Size,
+    Radius,
+    Color,
+    BorderColor,
+    Border,
+    /// All four corner radii of a rect
+    BorderRadius,
+    Rotation,
+    /// Alpha of the fill and the border color
+    Opacity,
+}
+
+/// Property value, properties with fewer components ignore the rest
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Value(pub [f32; 4]);
+
+impl Value {
+    pub fn lerp(self, other: Value, t: f32) -> Value {
+        let mut value = self.0;
+        for (a, b) in value.iter_mut().zip(other.0) {
+            *a += (b - *a) * t;
+        }
+        Value(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value([value, 0.0, 0.0, 0.0])
+    }
+}
+
+impl From<[f32; 2]> for Value {
+    fn from(value: [f32; 2]) -> Self {
+        Value([value[0], value[1], 0.0, 0.0])
+    }
+}
+
+impl From<[f32; 4]> for Value {
+    fn from(value: [f32; 4]) -> Self {
+        Value(value)
+    }
+}
+
+/// Shapes whose properties can be tweened. Properties a shape doesn't have read as `None`
+/// and ignore writes.
+pub trait Animatable {
+    fn get(&self, property: Property) -> Option<Value>;
+    fn set(&mut self, property: Property, value: Value);
+}
+
+impl Animatable for Rect {
+    fn get(&self, property: Property) -> Option<Value> {
+        Some(match property {
+            Property::Position => self.position.into(),
+            Property::Size => self.size.into(),
+            Property::Color => self.color.into(),
+            Property::BorderColor => self.border_color.into(),
+            Property::Border => self.border.into(),
+            Property::BorderRadius => self.border_radius.into(),
+            Property::Rotation => self.rotation.into(),
+            Property::Opacity => self.color[3].into(),
+            Property::Radius => return None,
+        })
+    }
+
+    fn set(&mut self, property: Property, Value(value): Value) {
+        match property {
+            Property::Position => self.position = [value[0], value[1]],
+            Property::Size => self.size = [value[0], value[1]],
+            Property::Color => self.color = value,
+            Property::BorderColor => self.border_color = value,
+            Property::Border => self.border = value[0],
+            Property::BorderRadius => self.border_radius = value,
+            Property::Rotation => self.rotation = value[0],
+            Property::Opacity => {
+                self.color[3] = value[0];
+                self.border_color[3] = value[0];
+            }
+            Property::Radius => {}
+        }
+    }
+}
+
+impl Animatable for Circle {
+    fn get(&self, property: Property) -> Option<Value> {
+        Some(match property {
+            Property::Position => self.position.into(),
+            Property::Radius => self.radius.into(),
+            Property::Color => self.color.into(),
+            Property::BorderColor => self.border_color.into(),
+            Property::Border => self.border.into(),
+            Property::Opacity => self.color[3].into(),
+            Property::Size | Property::BorderRadius | Property::Rotation => return None,
+        })
+    }
+
+    fn set(&mut self, property: Property, Value(value): Value) {
+        match property {
+            Property::Position => self.position = [value[0], value[1]],
+            Property::Radius => self.radius = value[0],
+            Property::Color => self.color = value,
+            Property::BorderColor => self.border_color = value,
+            Property::Border => self.border = value[0],
+            Property::Opacity => {
+                self.color[3] = value[0];
+                self.border_color[3] = value[0];
+            }
+            Property::Size | Property::BorderRadius | Property::Rotation => {}
+        }
+    }
+}
+
+/// The instances animations write to
+pub struct Scene<'a> {
+    pub rects: &'a mut [Rect],
+    pub circles: &'a mut [Circle],
+}
+
+impl Scene<'_> {
+    fn get(&self, target: Target, property: Property) -> Option<Value> {
+        match target {
+            Target::Rect(index) => self.rects.get(index)?.get(property),
+            Target::Circle(index) => self.circles.get(index)?.get(property),
+        }
+    }
+
+    fn set(&mut self, target: Target, property: Property, value: Value) {
+        match target {
+            Target::Rect(index) => {
+                if let Some(rect) = self.rects.get_mut(index) {
+                    rect.set(property, value);
+                }
+            }
+            Target::Circle(index) => {
+                if let Some(circle) = self.circles.get_mut(index) {
+                    circle.set(property, value);
+                }
+            }
+        }
+    }
+}
+
+/// Moves one property from its value at the start (or `from`) to `to`
+pub struct Tween {
+    pub target: Target,
+    pub property: Property,
+    pub from: Option<Value>,
+    pub to: Value,
+    pub duration: Duration,
+    pub easing: Easing,
+    elapsed: Duration,
+    /// Runs from `to` back to `from`, for yoyo repeats
+    reversed: bool,
+}
+
+impl Tween {
+    pub fn new(target: Target, property: Property, to: Value, duration: Duration) -> Self {
+        Self {
+            target,
+            property,
+            from: None,
+            to,
+            duration,
+            easing: Easing::default(),
+            elapsed: Duration::ZERO,
+            reversed: false,
+        }
+    }
+
+    pub(super) fn reset(&mut self, reversed: bool) {
+        self.elapsed = Duration::ZERO;
+        self.reversed = reversed;
+    }
+
+    /// Returns the part of `dt` that is left once the tween finished, `None` while it runs
+    pub(super) fn advance(&mut self, dt: Duration, scene: &mut Scene) -> Option<Duration> {
+        // The start value is taken on the first step, after any delay
+        if self.from.is_none() {
+            self.from = scene.get(self.target, self.property);
+        }
+
+        self.elapsed += dt;
+        let t = if self.duration.is_zero() {
+            1.0
+        } else {
+            (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        };
+        let t = if self.reversed { 1.0 - t } else { t };
+
+        if let Some(from) = self.from {
+            scene.set(
+                self.target,
+                self.property,
+                from.lerp(self.to, self.easing.apply(t)),
+            );
+        }
+
+        self.elapsed.checked_sub(self.duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn starts_from_the_current_value() {
+        let mut rects = [Rect {
+            rotation: 1.0,
+            ..Default::default()
+        }];
+        let mut scene = Scene {
+            rects: &mut rects,
+            circles: &mut [],
+        };
+        let mut tween = Tween::new(Target::Rect(0), Property::Rotation, 3.0.into(), ms(100));
+
+        assert_eq!(tween.advance(ms(25), &mut scene), None);
+        assert_eq!(scene.rects[0].rotation, 1.5);
+        assert_eq!(tween.from, Some(1.0.into()));
+
+        // The time past the end is handed back
+        assert_eq!(tween.advance(ms(100), &mut scene), Some(ms(25)));
+        assert_eq!(scene.rects[0].rotation, 3.0);
+    }
+
+    #[test]
+    fn runs_backwards_when_reversed() {
+        let mut circles = [Circle::default()];
+        let mut scene = Scene {
+            rects: &mut [],
+            circles: &mut circles,
+        };
+        let mut tween = Tween::new(Target::Circle(0), Property::Radius, 10.0.into(), ms(100));
+        tween.from = Some(2.0.into());
+        tween.easing = Easing::QuadIn;
+
+        tween.reset(true);
+        tween.advance(ms(50), &mut scene);
+        assert_eq!(scene.circles[0].radius, 2.0 + 8.0 * 0.25);
+        assert_eq!(tween.advance(ms(50), &mut scene), Some(Duration::ZERO));
+        assert_eq!(scene.circles[0].radius, 2.0);
+    }
+
+    #[test]
+    fn ignores_what_the_target_doesnt_have() {
+        let mut circles = [Circle::default()];
+        let mut scene = Scene {
+            rects: &mut [],
+            circles: &mut circles,
+        };
+
+        // Circles have no rotation, and there is no second circle
+        for target in [Target::Circle(0), Target::Circle(1)] {
+            let mut tween = Tween::new(target, Property::Rotation, 1.0.into(), ms(10));
+            assert_eq!(tween.advance(ms(20), &mut scene), Some(ms(10)));
+            assert_eq!(tween.from, None);
+        }
+        assert_eq!(scene.circles[0].position, Circle::default().position);
+
+        // Takes no time at all, the whole step is left over
+        let mut tween = Tween::new(Target::Circle(0), Property::Radius, 4.0.into(), ms(0));
+        assert_eq!(tween.advance(ms(16), &mut scene), Some(ms(16)));
+        assert_eq!(scene.circles[0].radius, 4.0);
+    }
+}
@@ -0,0 +1,297 @@
+mod tournament;
+mod view;
+pub use tournament::{MatchData, Tournament};
+pub use view::{BracketView, ConnectorLine, Label};
+
+/// Index into `Bracket::participants`
+pub type ParticipantId = usize;
+
+/// Position of a match, round 0 is the first round
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MatchId {
+    pub round: usize,
+    pub index: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Match {
+    /// `None` while the slot waits for the winner of an earlier match, or for a bye
+    pub participants: [Option<ParticipantId>; 2],
+    pub scores: [Option<u32>; 2],
+    pub winner: Option<ParticipantId>,
+    /// Score of every single game, in the order they were played
+    pub games: Vec<[u32; 2]>,
+    /// Where the match is streamed, as shown to the user
+    pub stream: Option<String>,
+    /// Start time as shown to the user, e.g. "SAT 18:00 UTC"
+    pub scheduled: Option<String>,
+}
+
+impl Match {
+    pub fn contains(&self, participant: ParticipantId) -> bool {
+        self.participants.contains(&Some(participant))
+    }
+}
+
+/// Single elimination bracket. The winner of match `i` in a round plays in match `i / 2`
+/// of the next round, in slot `i % 2`. The default bracket has no matches at all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bracket {
+    pub participants: Vec<String>,
+    rounds: Vec<Vec<Match>>,
+}
+
+impl Bracket {
+    /// Seeds `participants` in order, the first one is the top seed. The field is padded with
+    /// byes to a power of two and placed the usual way, so the top seeds get the byes and can
+    /// only meet late. Participants without an opponent advance right away.
+    pub fn single_elimination(participants: Vec<String>) -> Self {
+        let size = participants.len().max(2).next_power_of_two();
+
+        let mut rounds = Vec::new();
+        let mut matches = size / 2;
+        while matches > 0 {
+            rounds.push(vec![Match::default(); matches]);
+            matches /= 2;
+        }
+
+        let mut bracket = Self {
+            participants,
+            rounds,
+        };
+
+        for (slot, seed) in seed_order(size).into_iter().enumerate() {
+            let participant = (seed < bracket.participants.len()).then_some(seed);
+            bracket.rounds[0][slot / 2].participants[slot % 2] = participant;
+        }
+
+        for index in 0..bracket.rounds[0].len() {
+            if let [Some(participant), None] | [None, Some(participant)] =
+                bracket.rounds[0][index].participants
+            {
+                bracket.advance(MatchId { round: 0, index }, participant);
+            }
+        }
+
+        bracket
+    }
+
+    pub fn rounds(&self) -> &[Vec<Match>] {
+        &self.rounds
+    }
+
+    pub fn get(&self, id: MatchId) -> Option<&Match> {
+        self.rounds.get(id.round)?.get(id.index)
+    }
+
+    pub fn get_mut(&mut self, id: MatchId) -> Option<&mut Match> {
+        self.rounds.get_mut(id.round)?.get_mut(id.index)
+    }
+
+    /// `None` for an id that isn't in `participants`
+    pub fn name(&self, participant: ParticipantId) -> Option<&str> {
+        self.participants.get(participant).map(String::as_str)
+    }
+
+    /// The match the winner of `id` plays next, `None` for the final
+    pub fn next(&self, id: MatchId) -> Option<MatchId> {
+        let next = MatchId {
+            round: id.round + 1,
+            index: id.index / 2,
+        };
+        self.get(next).map(|_| next)
+    }
+
+    /// Records the scores and moves the winner into the next match. Ties and matches
+    /// without both participants are ignored.
+    pub fn set_result(&mut self, id: MatchId, scores: [u32; 2]) {
+        let Some(game) = self.get_mut(id) else {
+            return;
+        };
+        let [Some(first), Some(second)] = game.participants else {
+            return;
+        };
+        if scores[0] == scores[1] {
+            return;
+        }
+
+        game.scores = [Some(scores[0]), Some(scores[1])];
+        let winner = if scores[0] > scores[1] { first } else { second };
+        self.advance(id, winner);
+    }
+
+    /// Moves `winner` on, through every later match that only gets empty first round
+    /// matches as the other opponent
+    fn advance(&mut self, id: MatchId, winner: ParticipantId) {
+        self.rounds[id.round][id.index].winner = Some(winner);
+
+        if let Some(next) = self.next(id) {
+            self.rounds[next.round][next.index].participants[id.index % 2] = Some(winner);
+
+            let opponent = MatchId {
+                round: id.round,
+                index: id.index ^ 1,
+            };
+            if self.is_empty_branch(opponent) {
+                self.advance(next, winner);
+            }
+        }
+    }
+
+    /// Whether no participant can ever come out of `id`, because every first round match
+    /// leading to it is made of byes only
+    fn is_empty_branch(&self, id: MatchId) -> bool {
+        if id.round == 0 {
+            return self
+                .get(id)
+                .is_none_or(|game| game.participants == [None, None]);
+        }
+
+        let earlier = |index| MatchId {
+            round: id.round - 1,
+            index,
+        };
+        self.is_empty_branch(earlier(id.index * 2))
+            && self.is_empty_branch(earlier(id.index * 2 + 1))
+    }
+
+    /// Every match `participant` played or is about to play, in round order
+    pub fn path(&self, participant: ParticipantId) -> Vec<MatchId> {
+        let mut path = Vec::new();
+        for (round, matches) in self.rounds.iter().enumerate() {
+            if let Some(index) = matches.iter().position(|m| m.contains(participant)) {
+                path.push(MatchId { round, index });
+            }
+        }
+
+        path
+    }
+}
+
+/// Seed of every first round slot, counted from 0. Each pair of neighbors adds up to
+/// `size - 1`, and the better half of the seeds never meets before the final.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order
+            .iter()
+            .flat_map(|&seed| [seed, count - 1 - seed])
+            .collect();
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(entrants: usize) -> Bracket {
+        let names = (0..entrants).map(|i| format!("Team {}", i)).collect();
+        Bracket::single_elimination(names)
+    }
+
+    fn id(round: usize, index: usize) -> MatchId {
+        MatchId { round, index }
+    }
+
+    #[test]
+    fn seeds_eight_entrants() {
+        assert_eq!(seed_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+
+        let bracket = seeded(8);
+        assert_eq!(bracket.rounds().len(), 3);
+        assert_eq!(bracket.rounds()[0][0].participants, [Some(0), Some(7)]);
+        assert_eq!(bracket.rounds()[0][3].participants, [Some(2), Some(5)]);
+        // Nobody has a bye, so nobody moved on yet
+        assert!(bracket.rounds()[1]
+            .iter()
+            .all(|m| m.participants == [None, None]));
+    }
+
+    #[test]
+    fn gives_byes_to_the_top_seeds() {
+        let bracket = seeded(5);
+        let first: Vec<_> = bracket.rounds()[0].iter().map(|m| m.participants).collect();
+        assert_eq!(
+            first,
+            [
+                [Some(0), None],
+                [Some(3), Some(4)],
+                [Some(1), None],
+                [Some(2), None],
+            ]
+        );
+        assert_eq!(bracket.get(id(0, 0)).unwrap().winner, Some(0));
+        assert_eq!(bracket.get(id(0, 1)).unwrap().winner, None);
+        assert_eq!(bracket.rounds()[1][0].participants, [Some(0), None]);
+        assert_eq!(bracket.rounds()[1][1].participants, [Some(1), Some(2)]);
+        assert_eq!(bracket.rounds()[2][0].participants, [None, None]);
+
+        let bracket = seeded(6);
+        assert_eq!(bracket.rounds()[0][1].participants, [Some(3), Some(4)]);
+        assert_eq!(bracket.rounds()[0][3].participants, [Some(2), Some(5)]);
+        assert_eq!(bracket.rounds()[1][0].participants, [Some(0), None]);
+        assert_eq!(bracket.rounds()[1][1].participants, [Some(1), None]);
+    }
+
+    #[test]
+    fn moves_winners_forward() {
+        let mut bracket = seeded(4);
+        assert_eq!(bracket.rounds()[0][0].participants, [Some(0), Some(3)]);
+
+        bracket.set_result(id(0, 0), [1, 3]);
+        let game = bracket.get(id(0, 0)).unwrap();
+        assert_eq!(game.scores, [Some(1), Some(3)]);
+        assert_eq!(game.winner, Some(3));
+        assert_eq!(bracket.rounds()[1][0].participants, [Some(3), None]);
+
+        // Ties and matches still waiting for a participant change nothing
+        bracket.set_result(id(0, 1), [2, 2]);
+        assert_eq!(bracket.get(id(0, 1)).unwrap().winner, None);
+        bracket.set_result(id(1, 0), [1, 0]);
+        assert_eq!(bracket.get(id(1, 0)).unwrap().scores, [None, None]);
+
+        bracket.set_result(id(0, 1), [3, 0]);
+        assert_eq!(bracket.rounds()[1][0].participants, [Some(3), Some(1)]);
+        bracket.set_result(id(1, 0), [0, 2]);
+        assert_eq!(bracket.get(id(1, 0)).unwrap().winner, Some(1));
+        assert_eq!(bracket.next(id(1, 0)), None);
+    }
+
+    #[test]
+    fn follows_the_path_of_a_participant() {
+        let mut bracket = seeded(5);
+        assert_eq!(bracket.path(0), [id(0, 0), id(1, 0)]);
+        assert_eq!(bracket.path(4), [id(0, 1)]);
+
+        bracket.set_result(id(0, 1), [0, 2]);
+        bracket.set_result(id(1, 0), [1, 2]);
+        assert_eq!(bracket.path(4), [id(0, 1), id(1, 0), id(2, 0)]);
+        assert_eq!(bracket.path(3), [id(0, 1)]);
+        assert!(bracket.path(9).is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_a_tournament() {
+        let mut bracket = seeded(6);
+        bracket.set_result(id(0, 1), [3, 1]);
+        bracket.set_result(id(0, 3), [0, 2]);
+        bracket.set_result(id(1, 1), [2, 1]);
+        let game = bracket.get_mut(id(1, 0)).unwrap();
+        game.stream = Some("twitch.tv/sandbox".to_string());
+        game.games = vec![[1, 0], [0, 1]];
+
+        let tournament = Tournament::from_bracket(&bracket);
+        // Only matches with results or details are kept, byes follow from the seeding
+        let kept: Vec<_> = (tournament.matches.iter())
+            .map(|m| id(m.round, m.index))
+            .collect();
+        assert_eq!(kept, [id(0, 1), id(0, 3), id(1, 0), id(1, 1)]);
+        assert_eq!(tournament.bracket(), bracket);
+
+        let json = tournament.to_json().unwrap();
+        assert_eq!(Tournament::from_json(&json).unwrap().bracket(), bracket);
+    }
+}
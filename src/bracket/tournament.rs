@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Bracket, MatchId};
+
+/// A tournament as it is exchanged with other programs, e.g. the web frontend:
+///
+/// ```json
+/// {
+///     "participants": ["Aurora", "Blizzard", "Comet"],
+///     "matches": [
+///         { "round": 0, "index": 0, "scores": [3, 1], "stream": "twitch.tv/sandbox" }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    /// Seeded in order into a single elimination bracket
+    pub participants: Vec<String>,
+    #[serde(default)]
+    pub matches: Vec<MatchData>,
+}
+
+/// Everything known about one match, the participants follow from the earlier results
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchData {
+    pub round: usize,
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<[u32; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub games: Vec<[u32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<String>,
+}
+
+impl Tournament {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Participants and the matches of `bracket` that have results or details, so that
+    /// `bracket()` gives the same bracket back
+    pub fn from_bracket(bracket: &Bracket) -> Self {
+        let mut matches = Vec::new();
+        for (round, games) in bracket.rounds().iter().enumerate() {
+            for (index, game) in games.iter().enumerate() {
+                let data = MatchData {
+                    round,
+                    index,
+                    scores: match game.scores {
+                        [Some(first), Some(second)] => Some([first, second]),
+                        _ => None,
+                    },
+                    games: game.games.clone(),
+                    stream: game.stream.clone(),
+                    scheduled: game.scheduled.clone(),
+                };
+
+                let empty = MatchData {
+                    round,
+                    index,
+                    ..Default::default()
+                };
+                if data != empty {
+                    matches.push(data);
+                }
+            }
+        }
+
+        Self {
+            participants: bracket.participants.clone(),
+            matches,
+        }
+    }
+
+    /// Plays out the results round by round, matches that don't exist are skipped
+    pub fn bracket(&self) -> Bracket {
+        let mut bracket = Bracket::single_elimination(self.participants.clone());
+
+        let mut matches: Vec<&MatchData> = self.matches.iter().collect();
+        matches.sort_by_key(|data| (data.round, data.index));
+
+        for data in matches {
+            let id = MatchId {
+                round: data.round,
+                index: data.index,
+            };
+            if let Some(scores) = data.scores {
+                bracket.set_result(id, scores);
+            }
+            if let Some(game) = bracket.get_mut(id) {
+                game.games = data.games.clone();
+                game.stream = data.stream.clone();
+                game.scheduled = data.scheduled.clone();
+            }
+        }
+
+        bracket
+    }
+}
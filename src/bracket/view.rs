@@ -0,0 +1,366 @@
+use cgmath::Vector2;
+use std::ops::Range;
+
+use super::{Bracket, MatchId, ParticipantId};
+use crate::camera::Limits;
+use crate::rect::Rect;
+use crate::text::{Text, GLYPH_HEIGHT, GLYPH_WIDTH, LETTER_SPACING};
+use crate::theme::Theme;
+
+const CARD_WIDTH: f32 = 220.0;
+/// Height of one participant row, a card has two
+const SLOT_HEIGHT: f32 = 30.0;
+const CARD_HEIGHT: f32 = SLOT_HEIGHT * 2.0;
+/// Vertical space between the cards of the first round
+const MATCH_GAP: f32 = 24.0;
+/// Horizontal space between rounds, the connectors run through it
+const ROUND_GAP: f32 = 80.0;
+const PADDING: f32 = 10.0;
+/// Characters of the widest score plus the space before it, names get the rest of the row
+const SCORE_COLUMNS: u32 = 4;
+/// Alpha multiplier for everything off the highlighted path
+const DIM: f32 = 0.25;
+
+/// Rects of one match card, its background is the first
+struct Card {
+    id: MatchId,
+    slots: [Limits<f32>; 2],
+    rects: Range<usize>,
+}
+
+/// Line from a match to the one its winner plays next
+struct Connector {
+    from: MatchId,
+    /// Center line through the rects, from the card to the next one
+    points: [[f32; 2]; 4],
+    rects: Range<usize>,
+}
+
+/// Text of the bracket and the rects it was drawn with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub text: Text,
+    pub rects: Range<usize>,
+}
+
+/// Connector as a line, for exporters that draw lines instead of rects
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectorLine {
+    pub points: [[f32; 2]; 4],
+    pub width: f32,
+    pub rects: Range<usize>,
+}
+
+/// Lays out a `Bracket` as rects, rounds from left to right, and keeps track of
+/// which rects belong to which match so they can be highlighted
+pub struct BracketView {
+    instances: Vec<Rect>,
+    cards: Vec<Card>,
+    connectors: Vec<Connector>,
+    labels: Vec<Label>,
+    line_width: f32,
+    highlight_color: [f32; 4],
+}
+
+impl BracketView {
+    /// `origin` is the top left corner of the first match, colors are converted for `format`
+    pub fn new(
+        bracket: &Bracket,
+        origin: [f32; 2],
+        theme: &Theme,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let card_color = theme.palette.surface.for_format(format);
+        let border_color = theme.card.border_color.for_format(format);
+        let line_color = theme.connector.color.for_format(format);
+        let winner_color = theme.winner.for_format(format);
+        let loser_color = theme.loser.for_format(format);
+        let text_color = theme.palette.text.for_format(format);
+        let shadow_color = theme.palette.shadow.for_format(format);
+        let line_width = theme.connector.width;
+        let text_scale = theme.fonts.card;
+        let max_name = max_name(text_scale);
+
+        // Vertical center of every card, later rounds sit between the two matches feeding them
+        let mut centers: Vec<Vec<f32>> = Vec::new();
+        for (round, matches) in bracket.rounds().iter().enumerate() {
+            let round_centers = (0..matches.len())
+                .map(|index| match round {
+                    0 => origin[1] + CARD_HEIGHT / 2.0 + index as f32 * (CARD_HEIGHT + MATCH_GAP),
+                    _ => (centers[round - 1][index * 2] + centers[round - 1][index * 2 + 1]) / 2.0,
+                })
+                .collect();
+            centers.push(round_centers);
+        }
+        let left = |round: usize| origin[0] + round as f32 * (CARD_WIDTH + ROUND_GAP);
+
+        let mut instances = Vec::new();
+        let mut connectors = Vec::new();
+        let mut cards = Vec::new();
+        let mut labels = Vec::new();
+
+        // Connectors first, so the cards are drawn on top of their ends
+        for (round, matches) in bracket.rounds().iter().enumerate() {
+            for index in 0..matches.len() {
+                let id = MatchId { round, index };
+                let Some(next) = bracket.next(id) else {
+                    continue;
+                };
+
+                let start_x = left(round) + CARD_WIDTH;
+                let middle_x = start_x + ROUND_GAP / 2.0;
+                let start_y = centers[round][index];
+                // Ends at the row of the slot the winner moves into
+                let end_y = centers[next.round][next.index] - CARD_HEIGHT / 2.0
+                    + SLOT_HEIGHT * (index % 2) as f32
+                    + SLOT_HEIGHT / 2.0;
+
+                // From the top left corner with the full size, rects take the center
+                let line = |corner: [f32; 2], size: [f32; 2]| Rect {
+                    position: [corner[0] + size[0] / 2.0, corner[1] + size[1] / 2.0],
+                    size: [size[0] / 2.0, size[1] / 2.0],
+                    color: line_color,
+                    ..Default::default()
+                };
+
+                let first = instances.len();
+                instances.push(line(
+                    [start_x, start_y - line_width / 2.0],
+                    [ROUND_GAP / 2.0, line_width],
+                ));
+                instances.push(line(
+                    [
+                        middle_x - line_width / 2.0,
+                        start_y.min(end_y) - line_width / 2.0,
+                    ],
+                    [line_width, (end_y - start_y).abs() + line_width],
+                ));
+                instances.push(line(
+                    [middle_x, end_y - line_width / 2.0],
+                    [ROUND_GAP / 2.0, line_width],
+                ));
+
+                connectors.push(Connector {
+                    from: id,
+                    points: [
+                        [start_x, start_y],
+                        [middle_x, start_y],
+                        [middle_x, end_y],
+                        [start_x + ROUND_GAP, end_y],
+                    ],
+                    rects: first..instances.len(),
+                });
+            }
+        }
+
+        for (round, matches) in bracket.rounds().iter().enumerate() {
+            for (index, game) in matches.iter().enumerate() {
+                let x = left(round);
+                let top = centers[round][index] - CARD_HEIGHT / 2.0;
+                let first = instances.len();
+
+                instances.push(
+                    Rect {
+                        position: [x + CARD_WIDTH / 2.0, centers[round][index]],
+                        size: [CARD_WIDTH / 2.0, CARD_HEIGHT / 2.0],
+                        color: card_color,
+                        border: theme.card.border_width,
+                        border_color,
+                        border_radius: [theme.card.corner_radius; 4],
+                        ..Default::default()
+                    }
+                    .with_shadow(
+                        theme.card.shadow_offset,
+                        theme.card.shadow_blur,
+                        0.0,
+                        shadow_color,
+                    ),
+                );
+                // Half as thick as the border, it's shared by both rows
+                let divider = (theme.card.border_width / 2.0).max(1.0);
+                instances.push(Rect {
+                    position: [x + CARD_WIDTH / 2.0, top + SLOT_HEIGHT],
+                    size: [CARD_WIDTH / 2.0, divider / 2.0],
+                    color: border_color,
+                    ..Default::default()
+                });
+
+                let slots: [Limits<f32>; 2] = std::array::from_fn(|slot| Limits {
+                    left: x,
+                    right: x + CARD_WIDTH,
+                    bottom: top + (slot + 1) as f32 * SLOT_HEIGHT,
+                    top: top + slot as f32 * SLOT_HEIGHT,
+                });
+
+                for (slot, bounds) in slots.iter().enumerate() {
+                    let participant = game.participants[slot];
+                    let name = match participant {
+                        Some(participant) => bracket
+                            .name(participant)
+                            .unwrap_or_default()
+                            .chars()
+                            .take(max_name)
+                            .collect(),
+                        None if round == 0 => "BYE".to_string(),
+                        None => "TBD".to_string(),
+                    };
+                    let color = match (participant, game.winner) {
+                        (Some(participant), Some(winner)) if participant == winner => winner_color,
+                        (Some(_), Some(_)) => loser_color,
+                        _ => text_color,
+                    };
+                    let text_top =
+                        bounds.top + (SLOT_HEIGHT - GLYPH_HEIGHT as f32 * text_scale) / 2.0;
+
+                    let mut add_label = |text: Text| {
+                        let start = instances.len();
+                        instances.extend(text.rects());
+                        labels.push(Label {
+                            text,
+                            rects: start..instances.len(),
+                        });
+                    };
+
+                    add_label(Text::new(name, [x + PADDING, text_top], text_scale, color));
+
+                    if let Some(score) = game.scores[slot] {
+                        let mut text =
+                            Text::new(score.to_string(), [0.0, text_top], text_scale, color);
+                        text.position[0] = x + CARD_WIDTH - PADDING - text.size()[0];
+                        add_label(text);
+                    }
+                }
+
+                cards.push(Card {
+                    id: MatchId { round, index },
+                    slots,
+                    rects: first..instances.len(),
+                });
+            }
+        }
+
+        Self {
+            instances,
+            cards,
+            connectors,
+            labels,
+            line_width,
+            highlight_color: theme.palette.highlight.for_format(format),
+        }
+    }
+
+    /// Names and scores, their rects are part of `rects`
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// Lines between the matches, their rects are part of `rects`
+    pub fn connector_lines(&self) -> impl Iterator<Item = ConnectorLine> + '_ {
+        self.connectors.iter().map(|connector| ConnectorLine {
+            points: connector.points,
+            width: self.line_width,
+            rects: connector.rects.clone(),
+        })
+    }
+
+    /// Rects of the whole bracket without any highlight
+    pub fn rects(&self) -> &[Rect] {
+        &self.instances
+    }
+
+    /// World space box around the card of match `id`
+    pub fn card_bounds(&self, id: MatchId) -> Option<Limits<f32>> {
+        let card = self.cards.iter().find(|card| card.id == id)?;
+
+        Some(Limits {
+            bottom: card.slots[1].bottom,
+            ..card.slots[0]
+        })
+    }
+
+    /// Match whose card is under `point`
+    pub fn match_at(&self, point: Vector2<f32>) -> Option<MatchId> {
+        self.cards
+            .iter()
+            .find(|card| card.slots.iter().any(|slot| contains(slot, point)))
+            .map(|card| card.id)
+    }
+
+    /// Participant in the card row under `point`, `None` over empty slots
+    pub fn participant_at(&self, bracket: &Bracket, point: Vector2<f32>) -> Option<ParticipantId> {
+        for card in &self.cards {
+            for (slot, bounds) in card.slots.iter().enumerate() {
+                if contains(bounds, point) {
+                    return bracket.get(card.id)?.participants[slot];
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Highlights the matches and connectors on the path of `participant` and dims everything
+    /// else, `None` restores the normal colors. `instances` are the rects from `rects`,
+    /// only their colors are changed.
+    pub fn highlight(
+        &self,
+        bracket: &Bracket,
+        participant: Option<ParticipantId>,
+        instances: &mut [Rect],
+    ) {
+        for (rect, base) in instances.iter_mut().zip(&self.instances) {
+            rect.color = base.color;
+            rect.border_color = base.border_color;
+            rect.shadow_color = base.shadow_color;
+        }
+
+        let Some(participant) = participant else {
+            return;
+        };
+        let path = bracket.path(participant);
+
+        for card in &self.cards {
+            let rects = &mut instances[card.rects.clone()];
+            if path.contains(&card.id) {
+                rects[0].border_color = self.highlight_color;
+            } else {
+                rects.iter_mut().for_each(dim);
+            }
+        }
+
+        for connector in &self.connectors {
+            // Only the matches they won lead further along their path
+            let won = bracket
+                .get(connector.from)
+                .is_some_and(|game| game.winner == Some(participant));
+
+            for rect in &mut instances[connector.rects.clone()] {
+                if won {
+                    rect.color = self.highlight_color;
+                } else {
+                    dim(rect);
+                }
+            }
+        }
+    }
+}
+
+fn contains(bounds: &Limits<f32>, point: Vector2<f32>) -> bool {
+    point.x >= bounds.left
+        && point.x <= bounds.right
+        && point.y >= bounds.top
+        && point.y <= bounds.bottom
+}
+
+/// Characters of a name that fit next to a three digit score at `text_scale`
+fn max_name(text_scale: f32) -> usize {
+    let column = (GLYPH_WIDTH + LETTER_SPACING) as f32 * text_scale;
+    let room = CARD_WIDTH - PADDING * 2.0 - SCORE_COLUMNS as f32 * column;
+    (room / column).max(1.0) as usize
+}
+
+fn dim(rect: &mut Rect) {
+    rect.color[3] *= DIM;
+    rect.border_color[3] *= DIM;
+    rect.shadow_color[3] *= DIM;
+}
@@ -8,6 +8,16 @@ pub struct Camera {
     pub offset: Vector2<f32>,
 }
 
+/// Which camera a primitive is drawn with
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Space {
+    /// Moves with pan and zoom
+    World,
+    /// Fixed on screen in logical pixels, drawn on top of the world
+    Screen,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Limits<T> {
     pub left: T,
     pub right: T,
@@ -15,18 +25,129 @@ pub struct Limits<T> {
     pub top: T,
 }
 
+impl Limits<f32> {
+    /// Whether the two areas share any point, `top` is the smaller y
+    pub fn overlaps(&self, other: &Limits<f32>) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.top <= other.bottom
+            && other.top <= self.bottom
+    }
+
+    pub fn center(&self) -> [f32; 2] {
+        [
+            (self.left + self.right) / 2.0,
+            (self.top + self.bottom) / 2.0,
+        ]
+    }
+
+    /// Half the width and height, the size a `Rect` covering this area has
+    pub fn half_size(&self) -> [f32; 2] {
+        [
+            (self.right - self.left) / 2.0,
+            (self.bottom - self.top) / 2.0,
+        ]
+    }
+
+    /// Smallest area containing both
+    pub fn union(&self, other: &Limits<f32>) -> Limits<f32> {
+        Limits {
+            left: self.left.min(other.left),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+            top: self.top.min(other.top),
+        }
+    }
+}
+
 impl Camera {
+    /// Camera mapping one world unit to one logical pixel with the origin in the top left,
+    /// for things drawn in screen space. A `scale_factor` of 1 maps to physical pixels.
+    pub fn screen(size: PhysicalSize<u32>, scale_factor: f64) -> Self {
+        let logical = size.to_logical::<f32>(scale_factor);
+
+        Self {
+            mouse_pos: vec2(0.0, 0.0),
+            limits: Limits {
+                left: 0.0,
+                right: logical.width,
+                bottom: logical.height,
+                top: 0.0,
+            },
+            zoom: 1.0 / scale_factor as f32,
+            offset: vec2(0.0, 0.0),
+        }
+    }
+
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let projection = cgmath::ortho(
+        cgmath::ortho(
             self.limits.left,
             self.limits.right,
             self.limits.bottom,
             self.limits.top,
             2.0,
             0.0,
-        );
+        )
+    }
+
+    /// World point in the middle of the view
+    pub fn center(&self) -> Vector2<f32> {
+        vec2(
+            (self.limits.left + self.limits.right) / 2.0,
+            (self.limits.top + self.limits.bottom) / 2.0,
+        )
+    }
+
+    /// Centers the view on `center` at `zoom`, independent of what was in view before
+    pub fn look_at(&mut self, center: Vector2<f32>, zoom: f32, window_size: PhysicalSize<u32>) {
+        self.limits = Limits {
+            left: center.x,
+            right: center.x,
+            bottom: center.y,
+            top: center.y,
+        };
+        self.set_zoom(zoom, window_size);
+    }
+
+    /// Zooms around the center of the view, `zoom` is in world units per pixel
+    pub fn set_zoom(&mut self, zoom: f32, window_size: PhysicalSize<u32>) {
+        let Vector2 {
+            x: center_x,
+            y: center_y,
+        } = self.center();
+        let half_width = window_size.width as f32 * zoom / 2.0;
+        let half_height = window_size.height as f32 * zoom / 2.0;
+
+        self.zoom = zoom;
+        self.limits = Limits {
+            left: center_x - half_width,
+            right: center_x + half_width,
+            bottom: center_y + half_height,
+            top: center_y - half_height,
+        };
+    }
+
+    /// Centers `bounds` in the view and zooms so they fit with a small margin
+    pub fn fit(&mut self, bounds: Limits<f32>, window_size: PhysicalSize<u32>) {
+        let width = (bounds.right - bounds.left) / window_size.width as f32;
+        let height = (bounds.bottom - bounds.top) / window_size.height as f32;
 
-        return projection;
+        self.limits = bounds;
+        self.set_zoom(width.max(height) * 1.1, window_size);
+    }
+
+    /// Pixel position of a world space point, the inverse of `get_absolute_mouse_pos`
+    pub fn world_to_screen(
+        &self,
+        point: Vector2<f32>,
+        window_size: PhysicalSize<u32>,
+    ) -> Vector2<f32> {
+        vec2(
+            (point.x - self.limits.left) / (self.limits.right - self.limits.left)
+                * window_size.width as f32,
+            (point.y - self.limits.top) / (self.limits.bottom - self.limits.top)
+                * window_size.height as f32,
+        )
     }
 
     pub fn get_absolute_mouse_pos(
@@ -47,7 +168,7 @@ impl Camera {
         // Multiply inverted matrix with screen space mouse position to get world spaces coordinates
         let worldspace_mouse_pos = inverted * mouse;
 
-        return vec2(worldspace_mouse_pos.x, worldspace_mouse_pos.y);
+        vec2(worldspace_mouse_pos.x, worldspace_mouse_pos.y)
     }
 }
 
@@ -55,17 +176,29 @@ impl Camera {
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
+    /// Size of one screen pixel in world units, used to pad quads for anti-aliasing
+    pub pixel_size: f32,
+    _padding: [f32; 3],
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
             view_proj: cgmath::Matrix4::identity().into(),
+            pixel_size: 1.0,
+            _padding: [0.0; 3],
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
         self.view_proj = camera.build_view_projection_matrix().into();
+        self.pixel_size = camera.zoom;
     }
 
     pub fn get_absolute_mouse_pos(
@@ -73,6 +206,6 @@ impl CameraUniform {
         camera: &Camera,
         window_size: PhysicalSize<u32>,
     ) -> Vector2<f32> {
-        return camera.get_absolute_mouse_pos(self.view_proj.into(), window_size);
+        camera.get_absolute_mouse_pos(self.view_proj.into(), window_size)
     }
 }
@@ -1,13 +1,28 @@
 use wgpu::vertex_attr_array;
 
+use crate::camera::Limits;
+use crate::gradient::GradientId;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Circle {
     pub position: [f32; 2],
+    /// Straight alpha color in surface space, see `Color::for_format`
     pub color: [f32; 4],
     pub radius: f32,
     pub border: f32,
     pub border_color: [f32; 4],
+    /// Gradient used instead of `color`, 0 for none
+    pub fill_gradient: GradientId,
+    /// Gradient used instead of `border_color`, 0 for none
+    pub border_gradient: GradientId,
+    /// Shadow offset in world units
+    pub shadow_offset: [f32; 2],
+    pub shadow_blur: f32,
+    /// Grows (or shrinks when negative) the shadow before blurring
+    pub shadow_spread: f32,
+    /// Fully transparent disables the shadow. A glow is a shadow without offset.
+    pub shadow_color: [f32; 4],
 }
 
 impl Default for Circle {
@@ -18,16 +33,49 @@ impl Default for Circle {
             radius: 50.0,
             border: 0.0,
             border_color: [1.0; 4],
+            fill_gradient: 0,
+            border_gradient: 0,
+            shadow_offset: [0.0; 2],
+            shadow_blur: 0.0,
+            shadow_spread: 0.0,
+            shadow_color: [0.0; 4],
         }
     }
 }
 
 impl Circle {
-    pub fn attributes() -> [wgpu::VertexAttribute; 5] {
-        vertex_attr_array!(1 => Float32x2, 2 => Float32x4, 3 => Float32, 4 => Float32, 5 => Float32x4)
+    pub fn attributes() -> [wgpu::VertexAttribute; 9] {
+        vertex_attr_array!(1 => Float32x2, 2 => Float32x4, 3 => Float32, 4 => Float32, 5 => Float32x4, 6 => Uint32, 7 => Uint32, 8 => Float32x4, 9 => Float32x4)
+    }
+
+    /// Shadow around the shape with the same outline. Circle shadows are drawn after all rects
+    /// and before all circles, so they fall on every rect but never on another circle.
+    pub fn with_shadow(self, offset: [f32; 2], blur: f32, spread: f32, color: [f32; 4]) -> Self {
+        Self {
+            shadow_offset: offset,
+            shadow_blur: blur,
+            shadow_spread: spread,
+            shadow_color: color,
+            ..self
+        }
+    }
+
+    /// Centered shadow, usually in a bright color
+    pub fn with_glow(self, blur: f32, color: [f32; 4]) -> Self {
+        self.with_shadow([0.0; 2], blur, 0.0, color)
+    }
+
+    /// Axis aligned box around the circle, without its shadow
+    pub fn bounds(&self) -> Limits<f32> {
+        Limits {
+            left: self.position[0] - self.radius,
+            right: self.position[0] + self.radius,
+            bottom: self.position[1] + self.radius,
+            top: self.position[1] - self.radius,
+        }
     }
 
-    pub fn layout(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout {
+    pub fn layout(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout<'_> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Circle>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Instance,
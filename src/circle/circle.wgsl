@@ -4,10 +4,16 @@ struct InstanceInput {
     [[location(3)]] radius: f32;
     [[location(4)]] border: f32;
     [[location(5)]] border_color: vec4<f32>;
+    [[location(6)]] fill_gradient: u32;
+    [[location(7)]] border_gradient: u32;
+    // Offset, blur and spread
+    [[location(8)]] shadow: vec4<f32>;
+    [[location(9)]] shadow_color: vec4<f32>;
 };
 
 struct CameraUniform {
     view_proj: mat4x4<f32>;
+    pixel_size: f32;
 };
 
 [[group(0), binding(0)]]
@@ -20,10 +26,13 @@ struct VertexInput {
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    // Position relative to the circle center in world units
     [[location(1)]] local: vec2<f32>;
     [[location(2)]] border: f32;
     [[location(3)]] border_color: vec4<f32>;
     [[location(4)]] radius: f32;
+    [[location(5), interpolate(flat)]] fill_gradient: u32;
+    [[location(6), interpolate(flat)]] border_gradient: u32;
 };
 
 [[stage(vertex)]]
@@ -31,37 +40,94 @@ fn vs_main(
     model: VertexInput,
     instance: InstanceInput
 ) -> VertexOutput {
-    var transform: mat4x4<f32> = mat4x4<f32>(
-        vec4<f32>(instance.radius * 2.0, 0.0, 0.0, 0.0),
-        vec4<f32>(0.0, instance.radius * 2.0, 0.0, 0.0),
-        vec4<f32>(0.0, 0.0, 1.0, 0.0),
-        vec4<f32>(instance.position, 0.0, 1.0),
-    );
+    // Pad the quad by a pixel so the anti-aliased edge isn't clipped
+    let local = model.v_position * (instance.radius + camera.pixel_size);
 
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * transform * vec4<f32>(model.v_position, 0.0, 1.0);
+    out.clip_position = camera.view_proj * vec4<f32>(instance.position + local, 0.0, 1.0);
     out.color = instance.color;
-    out.local = model.v_position;
+    out.local = local;
     out.border = instance.border;
     out.border_color = instance.border_color;
     out.radius = instance.radius;
+    out.fill_gradient = instance.fill_gradient;
+    out.border_gradient = instance.border_gradient;
     return out;
 }
 
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let size = vec2<f32>(in.radius * 2.0);
+
     var color = in.color;
-    let R = 1.0; 
-    let BR = in.radius - in.border;
+    if (in.fill_gradient > 0u) {
+        color = gradient_color(in.fill_gradient, in.local, size);
+    }
+
+    let dist = length(in.local) - in.radius;
+    // Half a screen pixel in world units, keeps edges ~1px wide at any zoom
+    let aa = fwidth(dist) * 0.5;
+
+    if (in.border > 0.0) {
+        var border_color = in.border_color;
+        if (in.border_gradient > 0u) {
+            border_color = gradient_color(in.border_gradient, in.local, size);
+        }
+
+        let border_mix = smoothStep(-in.border - aa, -in.border + aa, dist);
+        color = mix(color, border_color, border_mix);
+    }
+
+    let alpha = 1.0 - smoothStep(-aa, aa, dist);
+
+    // Output premultiplied alpha to match the pipelines blend state
+    let a = color.w * alpha;
+    return vec4<f32>(color.xyz * a, a);
+}
+
+struct ShadowOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    // Position relative to the shadow center in world units
+    [[location(1)]] local: vec2<f32>;
+    // Radius including the spread
+    [[location(2)]] radius: f32;
+    [[location(3)]] blur: f32;
+};
+
+[[stage(vertex)]]
+fn vs_shadow(
+    model: VertexInput,
+    instance: InstanceInput
+) -> ShadowOutput {
+    var out: ShadowOutput;
+
+    // Collapse the quad when there is no shadow, so it costs no fragments
+    if (instance.shadow_color.w <= 0.0) {
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        return out;
+    }
 
-    let dist = length(in.local); 
-    let borderDist = length(in.local * in.radius); 
-    
-    if (dist > R) { discard; }
-    if (borderDist > BR) { color = in.border_color; }
+    let offset = instance.shadow.xy;
+    let blur = instance.shadow.z;
+    let radius = max(instance.radius + instance.shadow.w, 0.0);
 
-    let sm = smoothStep(R, R - 0.01, dist);
+    let local = model.v_position * (radius + blur + camera.pixel_size);
+
+    out.clip_position = camera.view_proj * vec4<f32>(instance.position + offset + local, 0.0, 1.0);
+    out.color = instance.shadow_color;
+    out.local = local;
+    out.radius = radius;
+    out.blur = blur;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_shadow(in: ShadowOutput) -> [[location(0)]] vec4<f32> {
+    let dist = length(in.local) - in.radius;
+    // Blur the edge over the blur radius, but never sharper than a pixel
+    let softness = max(in.blur, fwidth(dist) * 0.5);
+    let alpha = in.color.w * (1.0 - smoothStep(-softness, softness, dist));
 
-    return vec4<f32>(color.xyz, color.w * sm);
+    return vec4<f32>(in.color.xyz * alpha, alpha);
 }
- 
@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use instant::Instant;
+
+/// Longest delta handed to an update, so a stall doesn't fast forward every animation
+const MAX_DELTA: Duration = Duration::from_millis(100);
+/// Amount of frames kept for the statistics
+const HISTORY: usize = 120;
+
+/// Time that passed since the last frame, split into simulation steps
+#[derive(Copy, Clone, Debug)]
+pub struct Tick {
+    /// Real time since the last frame, clamped to `MAX_DELTA`
+    pub delta: Duration,
+    /// How many updates to run this frame, always 1 without a fixed step
+    pub steps: u32,
+    /// Time each update advances by
+    pub step: Duration,
+    /// How far the leftover time is into the next fixed step, in 0..1
+    pub alpha: f32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub average: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// Measures frame times with `Instant`, which uses `performance.now()` on the web
+pub struct FrameClock {
+    last_frame: Option<Instant>,
+    /// `None` runs one update per frame with the real delta
+    fixed_step: Option<Duration>,
+    accumulator: Duration,
+    history: VecDeque<Duration>,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self {
+            last_frame: None,
+            fixed_step: None,
+            accumulator: Duration::ZERO,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    pub fn fixed_step(&self) -> Option<Duration> {
+        self.fixed_step
+    }
+
+    pub fn set_fixed_step(&mut self, step: Option<Duration>) {
+        self.fixed_step = step.filter(|step| !step.is_zero());
+        self.accumulator = Duration::ZERO;
+    }
+
+    /// Restarts measuring from now, used when the loop was idle so the
+    /// next frame doesn't count the idle time
+    pub fn resume(&mut self) {
+        self.last_frame = Some(Instant::now());
+        self.accumulator = Duration::ZERO;
+    }
+
+    pub fn tick(&mut self) -> Tick {
+        self.tick_at(Instant::now())
+    }
+
+    fn tick_at(&mut self, now: Instant) -> Tick {
+        let delta = self
+            .last_frame
+            .map_or(Duration::ZERO, |last| now - last)
+            .min(MAX_DELTA);
+        self.last_frame = Some(now);
+
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(delta);
+
+        let step = match self.fixed_step {
+            Some(step) => step,
+            None => {
+                return Tick {
+                    delta,
+                    steps: 1,
+                    step: delta,
+                    alpha: 0.0,
+                }
+            }
+        };
+
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= step {
+            self.accumulator -= step;
+            steps += 1;
+        }
+
+        Tick {
+            delta,
+            steps,
+            step,
+            alpha: self.accumulator.as_secs_f32() / step.as_secs_f32(),
+        }
+    }
+
+    /// Recent frame times, oldest first
+    pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.history.iter().copied()
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        let frames = self.history.iter().filter(|delta| !delta.is_zero());
+        let count = frames.clone().count() as u32;
+        if count == 0 {
+            return FrameStats::default();
+        }
+
+        let total: Duration = frames.clone().sum();
+        let average = total / count;
+
+        FrameStats {
+            fps: 1.0 / average.as_secs_f32(),
+            average,
+            min: frames.clone().min().copied().unwrap_or_default(),
+            max: frames.max().copied().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// A clock with a fixed step whose last frame was at `start`
+    fn fixed(step: u64, start: Instant) -> FrameClock {
+        let mut clock = FrameClock::new();
+        clock.set_fixed_step(Some(ms(step)));
+        clock.tick_at(start);
+        clock
+    }
+
+    #[test]
+    fn counts_fixed_steps() {
+        let start = Instant::now();
+        let mut clock = fixed(10, start);
+
+        let tick = clock.tick_at(start + ms(35));
+        assert_eq!((tick.delta, tick.steps, tick.step), (ms(35), 3, ms(10)));
+        // The leftover 5ms make up the next step together with the next frame
+        assert!((tick.alpha - 0.5).abs() < 1e-4);
+        assert_eq!(clock.tick_at(start + ms(40)).steps, 1);
+        assert_eq!(clock.tick_at(start + ms(44)).steps, 0);
+    }
+
+    #[test]
+    fn keeps_the_leftover_after_a_long_frame() {
+        let start = Instant::now();
+        let mut clock = fixed(16, start);
+
+        let tick = clock.tick_at(start + ms(90));
+        assert_eq!(tick.steps, 5);
+        assert!((tick.alpha - 10.0 / 16.0).abs() < 1e-4);
+
+        let tick = clock.tick_at(start + ms(96));
+        assert_eq!(tick.steps, 1);
+        assert!(tick.alpha.abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamps_large_deltas() {
+        let start = Instant::now();
+        let mut clock = fixed(10, start);
+
+        let tick = clock.tick_at(start + Duration::from_secs(60));
+        assert_eq!(tick.delta, MAX_DELTA);
+        assert_eq!(tick.steps, 10);
+
+        // Without a fixed step the one update gets the clamped delta
+        clock.set_fixed_step(None);
+        let tick = clock.tick_at(start + Duration::from_secs(120));
+        assert_eq!(
+            (tick.delta, tick.steps, tick.step),
+            (MAX_DELTA, 1, MAX_DELTA)
+        );
+        assert_eq!(clock.stats().max, MAX_DELTA);
+    }
+
+    #[test]
+    fn skips_the_first_frame_in_the_stats() {
+        let start = Instant::now();
+        let mut clock = FrameClock::new();
+        assert_eq!(clock.tick_at(start).delta, Duration::ZERO);
+        clock.tick_at(start + ms(10));
+        clock.tick_at(start + ms(40));
+
+        let stats = clock.stats();
+        assert_eq!(
+            (stats.min, stats.max, stats.average),
+            (ms(10), ms(30), ms(20))
+        );
+        assert!((stats.fps - 50.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+
+/// A color as it is usually written down, with sRGB encoded channels in 0..1 and straight alpha.
+///
+/// The GPU wants linear values when the surface format is sRGB (the hardware encodes on write),
+/// so convert with [`Color::for_format`] before putting a color into instance data.
+///
+/// Serialized as `[r, g, b, a]`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[f32; 4]", into = "[f32; 4]")]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// sRGB channels in 0..255
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// sRGB channels in 0..255 and alpha in 0..1
+    pub fn rgba(r: u8, g: u8, b: u8, a: f32) -> Self {
+        Self::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a)
+    }
+
+    /// Parses `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa`, the leading `#` is optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // `from_str_radix` would take the sign in `+f` as well
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let expanded: String = match hex.len() {
+            3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 | 8 => hex.to_string(),
+            _ => return None,
+        };
+
+        let channel = |i: usize| u8::from_str_radix(&expanded[i * 2..i * 2 + 2], 16).ok();
+        let alpha = if expanded.len() == 8 {
+            channel(3)?
+        } else {
+            255
+        };
+
+        Some(Self::rgba(
+            channel(0)?,
+            channel(1)?,
+            channel(2)?,
+            alpha as f32 / 255.0,
+        ))
+    }
+
+    /// Hue in degrees, saturation and lightness in 0..1
+    pub fn hsl(h: f32, s: f32, l: f32) -> Self {
+        Self::hsla(h, s, l, 1.0)
+    }
+
+    pub fn hsla(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let m = l - chroma / 2.0;
+
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self::new(r + m, g + m, b + m, a)
+    }
+
+    /// CSS color names, case insensitive
+    pub fn named(name: &str) -> Option<Self> {
+        let hex = match name.to_ascii_lowercase().as_str() {
+            "transparent" => return Some(Self::TRANSPARENT),
+            "black" => "000000",
+            "white" => "ffffff",
+            "gray" | "grey" => "808080",
+            "silver" => "c0c0c0",
+            "red" => "ff0000",
+            "maroon" => "800000",
+            "orange" => "ffa500",
+            "yellow" => "ffff00",
+            "olive" => "808000",
+            "lime" => "00ff00",
+            "green" => "008000",
+            "aqua" | "cyan" => "00ffff",
+            "teal" => "008080",
+            "blue" => "0000ff",
+            "navy" => "000080",
+            "fuchsia" | "magenta" => "ff00ff",
+            "purple" => "800080",
+            "pink" => "ffc0cb",
+            "gold" => "ffd700",
+            _ => return None,
+        };
+
+        Self::from_hex(hex)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Linear channels with straight alpha
+    pub fn to_linear(self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+            self.a,
+        ]
+    }
+
+    /// Channels as the shaders have to output them for `format`
+    pub fn for_format(self, format: wgpu::TextureFormat) -> [f32; 4] {
+        if format.describe().srgb {
+            self.to_linear()
+        } else {
+            [self.r, self.g, self.b, self.a]
+        }
+    }
+
+    /// Inverse of `for_format`, for instance data read back from the GPU side
+    pub fn from_format(channels: [f32; 4], format: wgpu::TextureFormat) -> Self {
+        let [r, g, b, a] = channels;
+        if format.describe().srgb {
+            Self::new(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a)
+        } else {
+            Self::new(r, g, b, a)
+        }
+    }
+
+    /// Clear color for a render pass targeting `format`, premultiplied like everything we draw
+    pub fn to_wgpu(self, format: wgpu::TextureFormat) -> wgpu::Color {
+        let [r, g, b, a] = self.for_format(format);
+
+        wgpu::Color {
+            r: (r * a) as f64,
+            g: (g * a) as f64,
+            b: (b * a) as f64,
+            a: a as f64,
+        }
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        let (a, b): ([f32; 4], [f32; 4]) = (a.into(), b.into());
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(Color::from_hex("#f80"), Some(Color::rgb(255, 136, 0)));
+        assert_eq!(Color::from_hex("f80c"), Some(Color::rgba(255, 136, 0, 0.8)));
+        assert_eq!(Color::from_hex("#1a2B3c"), Some(Color::rgb(26, 43, 60)));
+        assert_eq!(
+            Color::from_hex("#1a2b3c80"),
+            Some(Color::rgba(26, 43, 60, 128.0 / 255.0))
+        );
+    }
+
+    #[test]
+    fn rejects_what_isnt_hex() {
+        for hex in [
+            "", "#", "#12", "#12345", "#1234567", "#ggg", "#+f+f+f", "#-1-1-1", "#ä1",
+        ] {
+            assert_eq!(Color::from_hex(hex), None, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn converts_hsl() {
+        assert_close(Color::hsl(0.0, 1.0, 0.5), Color::rgb(255, 0, 0));
+        assert_close(Color::hsl(120.0, 1.0, 0.5), Color::rgb(0, 255, 0));
+        assert_close(Color::hsl(240.0, 1.0, 0.25), Color::new(0.0, 0.0, 0.5, 1.0));
+        // Hues wrap around, gray has none
+        assert_close(Color::hsl(-120.0, 1.0, 0.5), Color::hsl(240.0, 1.0, 0.5));
+        assert_close(Color::hsl(77.0, 0.0, 0.5), Color::new(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(Color::hsla(30.0, 0.5, 0.5, 0.25).a, 0.25);
+    }
+
+    #[test]
+    fn looks_up_names() {
+        assert_eq!(Color::named("Navy"), Some(Color::rgb(0, 0, 128)));
+        assert_eq!(Color::named("grey"), Color::named("gray"));
+        assert_eq!(Color::named("transparent"), Some(Color::TRANSPARENT));
+        assert_eq!(Color::named("blurple"), None);
+    }
+
+    #[test]
+    fn round_trips_through_formats() {
+        let color = Color::rgba(200, 100, 30, 0.5);
+        for format in [
+            wgpu::TextureFormat::Bgra8UnormSrgb,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ] {
+            assert_close(Color::from_format(color.for_format(format), format), color);
+        }
+
+        // Only sRGB formats get linear channels, alpha stays straight
+        let linear = color.for_format(wgpu::TextureFormat::Bgra8UnormSrgb);
+        assert!(linear[0] < color.r && linear[3] == 0.5);
+        assert_eq!(
+            color.for_format(wgpu::TextureFormat::Rgba8Unorm),
+            <[f32; 4]>::from(color)
+        );
+    }
+}
@@ -0,0 +1,63 @@
+struct FilterUniform {
+    opacity: f32;
+    grayscale: f32;
+    brightness: f32;
+    blur: f32;
+    // One texel step along the blur axis
+    direction: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var source: texture_2d<f32>;
+[[group(0), binding(1)]]
+var source_sampler: sampler;
+
+[[group(1), binding(0)]]
+var<uniform> filter: FilterUniform;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// One triangle that covers the whole target, no vertex buffer needed
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Brightness, grayscale and opacity on premultiplied colors
+[[stage(fragment)]]
+fn fs_color(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSampleLevel(source, source_sampler, in.uv, 0.0);
+
+    var rgb = color.rgb * filter.brightness;
+    let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    rgb = mix(rgb, vec3<f32>(luma), filter.grayscale);
+
+    return vec4<f32>(rgb, color.a) * filter.opacity;
+}
+
+// One direction of a separable gaussian blur
+[[stage(fragment)]]
+fn fs_blur(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let sigma = max(filter.blur / 2.0, 0.0001);
+    let radius = clamp(i32(ceil(filter.blur)), 1, 64);
+
+    var sum = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var i: i32 = -radius; i <= radius; i = i + 1) {
+        let x = f32(i);
+        let weight = exp(-(x * x) / (2.0 * sigma * sigma));
+        let uv = in.uv + filter.direction * x;
+        sum = sum + textureSampleLevel(source, source_sampler, uv, 0.0) * weight;
+        weights = weights + weight;
+    }
+
+    return sum / weights;
+}
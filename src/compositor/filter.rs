@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Post-process settings for a group or the whole frame
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    /// Multiplies the alpha of the composited result
+    pub opacity: f32,
+    /// Gaussian blur radius in screen pixels, 0 disables the blur passes
+    pub blur: f32,
+    /// 0 keeps the colors, 1 is fully gray
+    pub grayscale: f32,
+    /// Multiplies the color channels
+    pub brightness: f32,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            blur: 0.0,
+            grayscale: 0.0,
+            brightness: 1.0,
+        }
+    }
+}
+
+impl Filter {
+    pub fn opacity(opacity: f32) -> Self {
+        Self {
+            opacity,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(super) fn uniform(&self, direction: [f32; 2]) -> FilterUniform {
+        FilterUniform {
+            opacity: self.opacity,
+            grayscale: self.grayscale,
+            brightness: self.brightness,
+            blur: self.blur,
+            direction,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct FilterUniform {
+    opacity: f32,
+    grayscale: f32,
+    brightness: f32,
+    blur: f32,
+    /// One texel step along the blur axis
+    direction: [f32; 2],
+    _padding: [f32; 2],
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::Filter;
+
+/// Instances that are rendered offscreen and composited as one unit, e.g. to fade a whole
+/// overlay. Groups are drawn in order on top of all ungrouped instances.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    pub rects: Range<u32>,
+    pub circles: Range<u32>,
+    pub filter: Filter,
+}
+
+impl Group {
+    /// Whether both ranges lie within `rects` and `circles` instances
+    pub fn fits(&self, rects: u32, circles: u32) -> bool {
+        self.rects.start <= self.rects.end
+            && self.rects.end <= rects
+            && self.circles.start <= self.circles.end
+            && self.circles.end <= circles
+    }
+
+    /// The group with its rects `offset` instances further back, e.g. behind the bracket
+    pub fn offset_rects(&self, offset: u32) -> Group {
+        Group {
+            rects: self.rects.start + offset..self.rects.end + offset,
+            circles: self.circles.clone(),
+            filter: self.filter,
+        }
+    }
+
+    /// The group cut down to `rects` and `circles` instances, for when instances were removed
+    /// after it was added
+    pub fn clamped(&self, rects: u32, circles: u32) -> Group {
+        let clamp = |range: &Range<u32>, total: u32| {
+            let end = range.end.min(total);
+            range.start.min(end)..end
+        };
+
+        Group {
+            rects: clamp(&self.rects, rects),
+            circles: clamp(&self.circles, circles),
+            filter: self.filter,
+        }
+    }
+}
+
+/// The parts of `0..total` that aren't covered by any of `ranges`
+pub fn ungrouped(total: u32, ranges: impl Iterator<Item = Range<u32>>) -> Vec<Range<u32>> {
+    let mut ranges: Vec<_> = ranges.filter(|range| !range.is_empty()).collect();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut gaps = Vec::new();
+    let mut start = 0;
+    for range in ranges {
+        if range.start > start {
+            gaps.push(start..range.start.min(total));
+        }
+        start = start.max(range.end);
+    }
+    if start < total {
+        gaps.push(start..total);
+    }
+
+    gaps
+}
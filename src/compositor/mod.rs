@@ -0,0 +1,324 @@
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, CommandEncoder, Device, RenderPipeline, Sampler,
+    SurfaceConfiguration, TextureView,
+};
+mod filter;
+mod group;
+pub use filter::Filter;
+pub use group::{ungrouped, Group};
+
+/// Offscreen color target that can be sampled by the compositor
+pub struct RenderTarget {
+    pub view: TextureView,
+    bind_group: BindGroup,
+}
+
+impl RenderTarget {
+    fn new(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("render_target_bind_group"),
+        });
+
+        Self { view, bind_group }
+    }
+}
+
+/// Which offscreen target a filter reads from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Source {
+    /// The whole scene, used for frame filters
+    Scene,
+    /// The group that was rendered last
+    Group,
+}
+
+/// Post-process passes that composite offscreen targets with a `Filter` applied
+pub struct Compositor {
+    color_pipeline: RenderPipeline,
+    blur_pipeline: RenderPipeline,
+    texture_bind_group_layout: BindGroupLayout,
+    filter_bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    width: u32,
+    height: u32,
+
+    pub scene: RenderTarget,
+    pub group: RenderTarget,
+    /// Holds the first half of the separable blur
+    scratch: RenderTarget,
+}
+
+impl Compositor {
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Compositor Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("compositor.wgsl").into()),
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("texture_bind_group_layout"),
+            });
+
+        let filter_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("filter_bind_group_layout"),
+            });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compositor Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout, &filter_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let create = |label, fs_entry| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry,
+                    targets: &[wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let color_pipeline = create("Compositor Color Pipeline", "fs_color");
+        let blur_pipeline = create("Compositor Blur Pipeline", "fs_blur");
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Compositor Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let target =
+            |label| RenderTarget::new(device, config, &texture_bind_group_layout, &sampler, label);
+        let scene = target("Scene Target");
+        let group = target("Group Target");
+        let scratch = target("Scratch Target");
+
+        Self {
+            color_pipeline,
+            blur_pipeline,
+            texture_bind_group_layout,
+            filter_bind_group_layout,
+            sampler,
+            width: config.width,
+            height: config.height,
+            scene,
+            group,
+            scratch,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.width = config.width;
+        self.height = config.height;
+
+        let target = |label| {
+            RenderTarget::new(
+                device,
+                config,
+                &self.texture_bind_group_layout,
+                &self.sampler,
+                label,
+            )
+        };
+        self.scene = target("Scene Target");
+        self.group = target("Group Target");
+        self.scratch = target("Scratch Target");
+    }
+
+    fn source(&self, source: Source) -> &RenderTarget {
+        match source {
+            Source::Scene => &self.scene,
+            Source::Group => &self.group,
+        }
+    }
+
+    /// Draws `source` onto `destination` with `filter` applied. Blurring happens in place,
+    /// so the source target is overwritten.
+    pub fn apply(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        source: Source,
+        destination: &TextureView,
+        filter: &Filter,
+    ) {
+        let source = self.source(source);
+
+        if filter.blur > 0.0 {
+            let horizontal = [1.0 / self.width as f32, 0.0];
+            let vertical = [0.0, 1.0 / self.height as f32];
+
+            self.pass(
+                device,
+                encoder,
+                &self.blur_pipeline,
+                source,
+                &self.scratch.view,
+                filter,
+                horizontal,
+                true,
+            );
+            self.pass(
+                device,
+                encoder,
+                &self.blur_pipeline,
+                &self.scratch,
+                &source.view,
+                filter,
+                vertical,
+                true,
+            );
+        }
+
+        self.pass(
+            device,
+            encoder,
+            &self.color_pipeline,
+            source,
+            destination,
+            filter,
+            [0.0; 2],
+            false,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn pass(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        source: &RenderTarget,
+        destination: &TextureView,
+        filter: &Filter,
+        direction: [f32; 2],
+        clear: bool,
+    ) {
+        // Every pass gets its own buffer, writes to a shared one would all land before the submit
+        let filter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filter Buffer"),
+            contents: bytemuck::cast_slice(&[filter.uniform(direction)]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let filter_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.filter_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: filter_buffer.as_entire_binding(),
+            }],
+            label: Some("filter_bind_group"),
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Compositor Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: destination,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &source.bind_group, &[]);
+        render_pass.set_bind_group(1, &filter_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,150 @@
+use softbuffer::GraphicsContext;
+use std::rc::Rc;
+use winit::event::{Event, WindowEvent};
+use winit::window::{Window, WindowId};
+
+use crate::color::Color;
+use crate::gpu::InitError;
+use crate::text::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH, LETTER_SPACING, LINE_SPACING};
+use crate::theme::Theme;
+
+/// Space around the message, in font pixels
+const MARGIN: u32 = 6;
+
+/// A native window without a device. It shows why with the bitmap font, drawn on the CPU.
+pub struct ErrorWindow {
+    window: Rc<Window>,
+    /// `None` if the platform can't take pixels from the CPU either, only the title is left then
+    context: Option<GraphicsContext<Rc<Window>>>,
+    message: String,
+}
+
+impl ErrorWindow {
+    pub fn new(window: Window, err: &InitError) -> Self {
+        let window = Rc::new(window);
+
+        // Safety: the context holds on to the window, so it can't outlive it
+        let context = match unsafe { GraphicsContext::new(Rc::clone(&window)) } {
+            Ok(context) => Some(context),
+            Err(err) => {
+                log::error!("Couldn't show the message in the window: {}", err);
+                None
+            }
+        };
+        window.request_redraw();
+
+        Self {
+            window,
+            context,
+            message: format!("Couldn't start drawing: {}", err),
+        }
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// Draws the message again whenever the window asks for it
+    pub fn handle_event<T>(&mut self, event: &Event<'_, T>) {
+        match event {
+            Event::RedrawRequested(id) if *id == self.id() => self.draw(),
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
+            } if *window_id == self.id() => self.window.request_redraw(),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self) {
+        let Some(context) = &mut self.context else {
+            return;
+        };
+        let size = self.window.inner_size();
+        let (width, height) = (
+            size.width.min(u16::MAX as u32),
+            size.height.min(u16::MAX as u32),
+        );
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let theme = Theme::default();
+        let mut pixels = vec![pixel(theme.background); (width * height) as usize];
+        let text = pixel(theme.palette.text);
+
+        // Font pixels as large as in the toolbar
+        let scale = (2.0 * self.window.scale_factor()).round().max(1.0) as u32;
+        let columns = (width / scale).saturating_sub(2 * MARGIN) / (GLYPH_WIDTH + LETTER_SPACING);
+        for (row, line) in wrap(&self.message, columns as usize).iter().enumerate() {
+            let top = MARGIN + row as u32 * (GLYPH_HEIGHT + LINE_SPACING);
+            for (column, c) in line.chars().enumerate() {
+                let left = MARGIN + column as u32 * (GLYPH_WIDTH + LETTER_SPACING);
+                for (x, bits) in glyph(c).iter().enumerate() {
+                    for y in (0..GLYPH_HEIGHT).filter(|y| bits & (1 << y) != 0) {
+                        let (px, py) = ((left + x as u32) * scale, (top + y) * scale);
+                        for py in py..(py + scale).min(height) {
+                            let row = (py * width) as usize;
+                            let span = px.min(width) as usize..(px + scale).min(width) as usize;
+                            pixels[row + span.start..row + span.end].fill(text);
+                        }
+                    }
+                }
+            }
+        }
+
+        context.set_buffer(&pixels, width as u16, height as u16);
+    }
+}
+
+/// `0RGB`, as softbuffer takes it
+fn pixel(color: Color) -> u32 {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(color.r) << 16) | (channel(color.g) << 8) | channel(color.b)
+}
+
+/// Lines of at most `columns` characters, broken between words where possible
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let columns = columns.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let len = line.chars().count();
+        if len > 0 && len + 1 + word.len() > columns {
+            lines.push(std::mem::take(&mut line));
+        } else if len > 0 {
+            line.push(' ');
+        }
+
+        // Words longer than a whole line are cut
+        while word.len() > columns {
+            let rest = word.split_off(columns);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(
+            wrap("Couldn't start drawing: no adapter", 14),
+            ["Couldn't start", "drawing: no", "adapter"]
+        );
+        assert_eq!(wrap("abcdefgh ij", 3), ["abc", "def", "gh", "ij"]);
+        assert_eq!(wrap("a  b", 0), ["a", "b"]);
+        assert!(wrap("", 10).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Exports of the world without the GPU, built from a `Drawing` of the retained scene
+mod pdf;
+mod png;
+mod svg;
+
+pub use pdf::{PageLayout, Paper, PdfOptions};
+pub use png::PngEncoder;
+
+use crate::camera::Limits;
+use crate::color::Color;
+use crate::gradient::Gradient;
+use crate::scene_file::{CircleData, RectData};
+
+/// Part of the world an export covers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// What the camera shows right now
+    View,
+    /// Everything in the world, with a small margin
+    Content,
+}
+
+/// Something drawn in the world, in world units with sRGB colors
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rect(RectData),
+    Circle(CircleData),
+    /// Straight segments through `points`, joined with sharp corners
+    Line {
+        points: Vec<[f32; 2]>,
+        width: f32,
+        color: Color,
+    },
+    /// A line of the bitmap font, `position` is the top left corner of the first glyph and
+    /// `scale` the size of one font pixel
+    Text {
+        content: String,
+        position: [f32; 2],
+        scale: f32,
+        color: Color,
+    },
+}
+
+/// The world as exporters see it, see `State::drawing`. The bracket is made of text and lines
+/// here, not the rects it is drawn with. Screen space, e.g. the toolbar, isn't part of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drawing {
+    pub bounds: Limits<f32>,
+    pub background: Color,
+    /// Table the gradient ids of the shapes refer to, starting at 1
+    pub gradients: Vec<Gradient>,
+    /// In drawing order, later shapes cover earlier ones
+    pub shapes: Vec<Shape>,
+}
+
+impl Shape {
+    /// Multiplies the alpha of every color, for groups with an opacity filter
+    pub fn faded(mut self, opacity: f32) -> Self {
+        let fade = |color: &mut Color| color.a *= opacity;
+        match &mut self {
+            Shape::Rect(rect) => {
+                fade(&mut rect.color);
+                fade(&mut rect.border_color);
+                fade(&mut rect.shadow_color);
+            }
+            Shape::Circle(circle) => {
+                fade(&mut circle.color);
+                fade(&mut circle.border_color);
+                fade(&mut circle.shadow_color);
+            }
+            Shape::Line { color, .. } | Shape::Text { color, .. } => fade(color),
+        }
+
+        self
+    }
+}
+
+impl Drawing {
+    pub fn width(&self) -> f32 {
+        self.bounds.right - self.bounds.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bounds.bottom - self.bounds.top
+    }
+
+    fn gradient(&self, id: u32) -> Option<&Gradient> {
+        self.gradients.get((id as usize).checked_sub(1)?)
+    }
+}
+
+/// Rounded to thousandths, without trailing zeros or exponents, which PDF doesn't accept.
+/// Neither PDF nor SVG have NaN or infinity, those become 0 so the file stays readable.
+fn num(value: f32) -> String {
+    if !value.is_finite() {
+        return "0".to_string();
+    }
+
+    // Adding zero turns -0 into 0, f64 keeps large values from overflowing
+    let rounded = (value as f64 * 1000.0).round() / 1000.0 + 0.0;
+    format!("{}", rounded)
+}
@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+
+use crate::color::Color;
+use crate::scene_file::{CircleData, RectData};
+use crate::text::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH, LETTER_SPACING};
+
+use super::{num, Drawing, Shape};
+
+/// Points per millimeter, PDF measures everything in 1/72 inch
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+/// Control point distance of a quarter circle drawn as one cubic bezier, relative to its radius
+const KAPPA: f32 = 0.552_284_8;
+
+/// ISO 216 paper sizes, posters are usually A2 or A1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Paper {
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+}
+
+impl Paper {
+    /// Width and height in millimeters, upright
+    pub fn size(self) -> [f32; 2] {
+        match self {
+            Paper::A0 => [841.0, 1189.0],
+            Paper::A1 => [594.0, 841.0],
+            Paper::A2 => [420.0, 594.0],
+            Paper::A3 => [297.0, 420.0],
+            Paper::A4 => [210.0, 297.0],
+        }
+    }
+
+    /// Parses `a0` to `a4`, in any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "a0" => Some(Paper::A0),
+            "a1" => Some(Paper::A1),
+            "a2" => Some(Paper::A2),
+            "a3" => Some(Paper::A3),
+            "a4" => Some(Paper::A4),
+            _ => None,
+        }
+    }
+}
+
+/// How the drawing is put on paper
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PageLayout {
+    /// Scaled to fill the printable area of a single page
+    Fit,
+    /// `scale` millimeters per world unit, spread over as many pages as that takes. The pages
+    /// go row by row and are cut at the margins, so they can be taped together.
+    Tiles { scale: f32 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PdfOptions {
+    pub paper: Paper,
+    /// Long side horizontal, which suits brackets since they grow to the right
+    pub landscape: bool,
+    /// Empty border around every page, in millimeters
+    pub margin: f32,
+    pub layout: PageLayout,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A2,
+            landscape: true,
+            margin: 10.0,
+            layout: PageLayout::Fit,
+        }
+    }
+}
+
+impl Drawing {
+    /// PDF document of the drawing. Shapes stay vectors and text uses the bitmap font of the
+    /// viewer, embedded as a Type 3 font. PDF has no blur, so shadows are left out, and
+    /// gradients are filled with the color halfway through them.
+    pub fn to_pdf(&self, options: &PdfOptions) -> Vec<u8> {
+        let [mut paper_width, mut paper_height] = options.paper.size();
+        if options.landscape {
+            std::mem::swap(&mut paper_width, &mut paper_height);
+        }
+        let page = [paper_width * POINTS_PER_MM, paper_height * POINTS_PER_MM];
+        let margin = (options.margin * POINTS_PER_MM).clamp(0.0, page[0].min(page[1]) / 2.0 - 1.0);
+        let area = [page[0] - 2.0 * margin, page[1] - 2.0 * margin];
+        let (width, height) = (self.width().max(1.0), self.height().max(1.0));
+
+        // Points per world unit, and the pages in each direction
+        let (scale, columns, rows) = match options.layout {
+            PageLayout::Fit => ((area[0] / width).min(area[1] / height), 1, 1),
+            PageLayout::Tiles { scale } => {
+                let scale = scale.max(f32::EPSILON) * POINTS_PER_MM;
+                let columns = (width * scale / area[0]).ceil().max(1.0) as u32;
+                let rows = (height * scale / area[1]).ceil().max(1.0) as u32;
+                (scale, columns, rows)
+            }
+        };
+        // Centers a drawing smaller than the printable area
+        let offset = [
+            (area[0] * columns as f32 - width * scale).max(0.0) / 2.0,
+            (area[1] * rows as f32 - height * scale).max(0.0) / 2.0,
+        ];
+
+        let mut pdf = Pdf::default();
+        let catalog = pdf.reserve();
+        let pages = pdf.reserve();
+        let font = font(&mut pdf);
+
+        let mut content = Content::default();
+        content.draw(self);
+        let states = content.alpha_states(&mut pdf);
+        let form = pdf.add_stream(
+            &format!(
+                "/Type /XObject /Subtype /Form /BBox [{} {} {} {}] \
+                 /Resources << /Font << /F1 {} 0 R >> /ExtGState << {} >> >>",
+                num(self.bounds.left),
+                num(self.bounds.top),
+                num(self.bounds.right),
+                num(self.bounds.bottom),
+                font,
+                states
+            ),
+            content.operators.as_bytes(),
+        );
+
+        let mut kids = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                // World y grows downwards, page y upwards
+                let left = margin + offset[0] - column as f32 * area[0];
+                let top = page[1] - margin - offset[1] + row as f32 * area[1];
+                let operators = format!(
+                    "q {} {} {} {} re W n {} 0 0 {} {} {} cm /X1 Do Q\n",
+                    num(margin),
+                    num(margin),
+                    num(area[0]),
+                    num(area[1]),
+                    num(scale),
+                    num(-scale),
+                    num(left - self.bounds.left * scale),
+                    num(top + self.bounds.top * scale),
+                );
+                let contents = pdf.add_stream("", operators.as_bytes());
+                kids.push(pdf.add(format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /XObject << /X1 {} 0 R >> >> /Contents {} 0 R >>",
+                    pages,
+                    num(page[0]),
+                    num(page[1]),
+                    form,
+                    contents
+                )));
+            }
+        }
+
+        let kids: Vec<String> = kids.iter().map(|kid| format!("{} 0 R", kid)).collect();
+        pdf.set(
+            pages,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                kids.len()
+            ),
+        );
+        pdf.set(
+            catalog,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", pages),
+        );
+
+        pdf.finish(catalog)
+    }
+}
+
+/// Type 3 font with one glyph per printable ASCII character, each drawn from the bitmap
+/// font as filled squares. One font pixel is an eighth of the font size.
+fn font(pdf: &mut Pdf) -> usize {
+    let advance = GLYPH_WIDTH + LETTER_SPACING;
+    let mut procs = Vec::new();
+    let mut names = Vec::new();
+
+    for code in 0x20u8..=0x7e {
+        let mut operators = format!("{} 0 0 0 {} {} d1\n", advance, GLYPH_WIDTH, GLYPH_HEIGHT);
+        for (x, bits) in glyph(code as char).iter().enumerate() {
+            let mut y = 0;
+            while y < GLYPH_HEIGHT {
+                if bits & (1 << y) == 0 {
+                    y += 1;
+                    continue;
+                }
+
+                let start = y;
+                while y < GLYPH_HEIGHT && bits & (1 << y) != 0 {
+                    y += 1;
+                }
+                // Rows count from the top, glyph space from the bottom
+                operators.push_str(&format!("{} {} 1 {} re\n", x, GLYPH_HEIGHT - y, y - start));
+            }
+        }
+        // A space has no path to fill
+        if operators.ends_with("re\n") {
+            operators.push_str("f\n");
+        }
+
+        let proc = pdf.add_stream("", operators.as_bytes());
+        procs.push(format!("/g{} {} 0 R", code, proc));
+        names.push(format!("/g{}", code));
+    }
+
+    let widths = vec![advance.to_string(); names.len()];
+    pdf.add(format!(
+        "<< /Type /Font /Subtype /Type3 /FontBBox [0 0 {} {}] /FontMatrix [{} 0 0 {} 0 0] \
+         /CharProcs << {} >> /Encoding << /Type /Encoding /Differences [32 {}] >> \
+         /FirstChar 32 /LastChar 126 /Widths [{}] /Resources << >> >>",
+        GLYPH_WIDTH,
+        GLYPH_HEIGHT,
+        num(1.0 / GLYPH_HEIGHT as f32),
+        num(1.0 / GLYPH_HEIGHT as f32),
+        procs.join(" "),
+        names.join(" "),
+        widths.join(" ")
+    ))
+}
+
+/// Content stream of the drawing in world units
+#[derive(Default)]
+struct Content {
+    operators: String,
+    /// Graphics state name of every alpha in use, by alpha in 1/255
+    alphas: HashMap<u8, String>,
+}
+
+impl Content {
+    fn draw(&mut self, drawing: &Drawing) {
+        self.fill_color(drawing.background);
+        self.operators.push_str(&format!(
+            "{} {} {} {} re f\n",
+            num(drawing.bounds.left),
+            num(drawing.bounds.top),
+            num(drawing.width()),
+            num(drawing.height())
+        ));
+
+        for shape in &drawing.shapes {
+            match shape {
+                Shape::Rect(rect) => self.rect(drawing, rect),
+                Shape::Circle(circle) => self.circle(drawing, circle),
+                Shape::Line {
+                    points,
+                    width,
+                    color,
+                } => self.line(points, *width, *color),
+                Shape::Text {
+                    content,
+                    position,
+                    scale,
+                    color,
+                } => self.text(content, *position, *scale, *color),
+            }
+        }
+    }
+
+    /// Rotated around its pivot, the border is a stroke inside the outline like on screen
+    fn rect(&mut self, drawing: &Drawing, rect: &RectData) {
+        let [x, y] = rect.top_left();
+        let [width, height] = rect.full_size();
+        let half = width.min(height) / 2.0;
+        let radii = rect.border_radius.map(|radius| radius.min(half));
+        let pivot = [x + rect.pivot[0] * width, y + rect.pivot[1] * height];
+
+        let (sin, cos) = rect.rotation.sin_cos();
+        self.operators.push_str(&format!(
+            "q {} {} {} {} {} {} cm\n",
+            num(cos),
+            num(sin),
+            num(-sin),
+            num(cos),
+            num(pivot[0]),
+            num(pivot[1])
+        ));
+        // Relative to the pivot from here on
+        let corner = [-rect.pivot[0] * width, -rect.pivot[1] * height];
+
+        let fill = fill_color(drawing, rect.fill_gradient, rect.color);
+        if fill.a > 0.0 {
+            self.fill_color(fill);
+            rounded_rect(&mut self.operators, corner, [width, height], radii);
+            self.operators.push_str("f\n");
+        }
+
+        let stroke = fill_color(drawing, rect.border_gradient, rect.border_color);
+        if rect.border > 0.0 && stroke.a > 0.0 {
+            let inset = rect.border / 2.0;
+            self.stroke_color(stroke, rect.border);
+            rounded_rect(
+                &mut self.operators,
+                [corner[0] + inset, corner[1] + inset],
+                [
+                    (width - rect.border).max(0.0),
+                    (height - rect.border).max(0.0),
+                ],
+                radii.map(|radius| (radius - inset).max(0.0)),
+            );
+            self.operators.push_str("S\n");
+        }
+
+        self.operators.push_str("Q\n");
+    }
+
+    fn circle(&mut self, drawing: &Drawing, circle: &CircleData) {
+        let fill = fill_color(drawing, circle.fill_gradient, circle.color);
+        if fill.a > 0.0 {
+            self.fill_color(fill);
+            ellipse(&mut self.operators, circle.position, circle.radius);
+            self.operators.push_str("f\n");
+        }
+
+        let stroke = fill_color(drawing, circle.border_gradient, circle.border_color);
+        if circle.border > 0.0 && stroke.a > 0.0 {
+            self.stroke_color(stroke, circle.border);
+            let radius = (circle.radius - circle.border / 2.0).max(0.0);
+            ellipse(&mut self.operators, circle.position, radius);
+            self.operators.push_str("S\n");
+        }
+    }
+
+    fn line(&mut self, points: &[[f32; 2]], width: f32, color: Color) {
+        let Some(([x, y], rest)) = points.split_first() else {
+            return;
+        };
+
+        self.stroke_color(color, width);
+        // Sharp corners, and no caps past the end points
+        self.operators
+            .push_str(&format!("0 j 0 J {} {} m", num(*x), num(*y)));
+        for [x, y] in rest {
+            self.operators
+                .push_str(&format!(" {} {} l", num(*x), num(*y)));
+        }
+        self.operators.push_str(" S\n");
+    }
+
+    /// The glyph origin is the bottom of the cell. The text matrix flips y back, which the
+    /// page turned upside down to match the world.
+    fn text(&mut self, content: &str, position: [f32; 2], scale: f32, color: Color) {
+        let encoded: String = content
+            .chars()
+            .map(|c| match c {
+                '(' | ')' | '\\' => format!("\\{}", c),
+                ' '..='~' => c.to_string(),
+                _ => "?".to_string(),
+            })
+            .collect();
+
+        self.fill_color(color);
+        self.operators.push_str(&format!(
+            "BT /F1 {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET\n",
+            num(GLYPH_HEIGHT as f32 * scale),
+            num(position[0]),
+            num(position[1] + GLYPH_HEIGHT as f32 * scale),
+            encoded
+        ));
+    }
+
+    fn fill_color(&mut self, color: Color) {
+        let state = self.alpha(color.a);
+        self.operators.push_str(&format!(
+            "/{} gs {} {} {} rg\n",
+            state,
+            num(color.r),
+            num(color.g),
+            num(color.b)
+        ));
+    }
+
+    fn stroke_color(&mut self, color: Color, width: f32) {
+        let state = self.alpha(color.a);
+        self.operators.push_str(&format!(
+            "/{} gs {} {} {} RG {} w\n",
+            state,
+            num(color.r),
+            num(color.g),
+            num(color.b),
+            num(width)
+        ));
+    }
+
+    /// Name of the graphics state for `alpha`, sets fill and stroke alpha alike
+    fn alpha(&mut self, alpha: f32) -> String {
+        let key = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let count = self.alphas.len();
+        self.alphas
+            .entry(key)
+            .or_insert_with(|| format!("A{}", count))
+            .clone()
+    }
+
+    /// Adds the graphics states and returns their resource dictionary entries
+    fn alpha_states(&self, pdf: &mut Pdf) -> String {
+        let mut alphas: Vec<(&u8, &String)> = self.alphas.iter().collect();
+        alphas.sort();
+
+        let entries: Vec<String> = alphas
+            .into_iter()
+            .map(|(alpha, name)| {
+                let alpha = num(*alpha as f32 / 255.0);
+                let state = pdf.add(format!(
+                    "<< /Type /ExtGState /ca {} /CA {} >>",
+                    alpha, alpha
+                ));
+                format!("/{} {} 0 R", name, state)
+            })
+            .collect();
+
+        entries.join(" ")
+    }
+}
+
+/// Color of a flat fill, or the middle of the gradient `id` if there is one
+fn fill_color(drawing: &Drawing, id: u32, color: Color) -> Color {
+    let Some(gradient) = drawing.gradient(id) else {
+        return color;
+    };
+
+    let mut previous = match gradient.stops.first() {
+        Some(&stop) => stop,
+        None => return color,
+    };
+    for &(offset, next) in &gradient.stops {
+        if offset >= 0.5 {
+            let t = if offset > previous.0 {
+                (0.5 - previous.0) / (offset - previous.0)
+            } else {
+                0.0
+            };
+            let mix = |a: f32, b: f32| a + (b - a) * t.clamp(0.0, 1.0);
+            let from = previous.1;
+            return Color::new(
+                mix(from.r, next.r),
+                mix(from.g, next.g),
+                mix(from.b, next.b),
+                mix(from.a, next.a),
+            );
+        }
+        previous = (offset, next);
+    }
+
+    previous.1
+}
+
+/// Path of a rect with the corners clockwise from the top left like `border_radius`, in a
+/// space where y grows downwards
+fn rounded_rect(operators: &mut String, position: [f32; 2], size: [f32; 2], radii: [f32; 4]) {
+    let [x, y] = position;
+    let (right, bottom) = (x + size[0], y + size[1]);
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+
+    if radii.iter().all(|&radius| radius <= 0.0) {
+        operators.push_str(&format!(
+            "{} {} {} {} re\n",
+            num(x),
+            num(y),
+            num(size[0]),
+            num(size[1])
+        ));
+        return;
+    }
+
+    // Each corner goes from the end of one edge to the start of the next
+    let corner = |operators: &mut String, from: [f32; 2], to: [f32; 2], vertex: [f32; 2]| {
+        let control = |point: [f32; 2]| {
+            [
+                point[0] + (vertex[0] - point[0]) * KAPPA,
+                point[1] + (vertex[1] - point[1]) * KAPPA,
+            ]
+        };
+        let (first, second) = (control(from), control(to));
+        operators.push_str(&format!(
+            " {} {} {} {} {} {} c",
+            num(first[0]),
+            num(first[1]),
+            num(second[0]),
+            num(second[1]),
+            num(to[0]),
+            num(to[1])
+        ));
+    };
+
+    operators.push_str(&format!("{} {} m", num(x + top_left), num(y)));
+    operators.push_str(&format!(" {} {} l", num(right - top_right), num(y)));
+    corner(
+        operators,
+        [right - top_right, y],
+        [right, y + top_right],
+        [right, y],
+    );
+    operators.push_str(&format!(" {} {} l", num(right), num(bottom - bottom_right)));
+    corner(
+        operators,
+        [right, bottom - bottom_right],
+        [right - bottom_right, bottom],
+        [right, bottom],
+    );
+    operators.push_str(&format!(" {} {} l", num(x + bottom_left), num(bottom)));
+    corner(
+        operators,
+        [x + bottom_left, bottom],
+        [x, bottom - bottom_left],
+        [x, bottom],
+    );
+    operators.push_str(&format!(" {} {} l", num(x), num(y + top_left)));
+    corner(operators, [x, y + top_left], [x + top_left, y], [x, y]);
+    operators.push_str(" h\n");
+}
+
+/// Circle path made of four quarter circle beziers
+fn ellipse(operators: &mut String, center: [f32; 2], radius: f32) {
+    let [x, y] = center;
+    let k = radius * KAPPA;
+    operators.push_str(&format!(
+        "{} {} m {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c h\n",
+        num(x + radius), num(y),
+        num(x + radius), num(y + k), num(x + k), num(y + radius), num(x), num(y + radius),
+        num(x - k), num(y + radius), num(x - radius), num(y + k), num(x - radius), num(y),
+        num(x - radius), num(y - k), num(x - k), num(y - radius), num(x), num(y - radius),
+        num(x + k), num(y - radius), num(x + radius), num(y - k), num(x + radius), num(y),
+    ));
+}
+
+/// Objects of a PDF file, numbered from 1 in the order they were added
+#[derive(Default)]
+struct Pdf {
+    objects: Vec<Vec<u8>>,
+}
+
+impl Pdf {
+    /// Number for an object whose contents depend on objects added later
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, dictionary: String) {
+        self.objects[id - 1] = dictionary.into_bytes();
+    }
+
+    fn add(&mut self, dictionary: String) -> usize {
+        let id = self.reserve();
+        self.set(id, dictionary);
+        id
+    }
+
+    /// Stream object, `entries` go into its dictionary next to the length
+    fn add_stream(&mut self, entries: &str, data: &[u8]) -> usize {
+        let separator = if entries.is_empty() { "" } else { " " };
+        let mut object = format!(
+            "<< {}{}/Length {} >>\nstream\n",
+            entries,
+            separator,
+            data.len()
+        )
+        .into_bytes();
+        object.extend_from_slice(data);
+        object.extend_from_slice(b"\nendstream");
+
+        let id = self.reserve();
+        self.objects[id - 1] = object;
+        id
+    }
+
+    /// The file with its cross-reference table, which lists the byte offset of every object
+    fn finish(self, root: usize) -> Vec<u8> {
+        // The comment with bytes above 127 marks the file as binary for transfer programs
+        let mut file = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(file.len());
+            file.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            file.extend_from_slice(object);
+            file.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = file.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            xref
+        ));
+        file.extend_from_slice(table.as_bytes());
+
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Limits;
+
+    fn drawing() -> Drawing {
+        Drawing {
+            bounds: Limits {
+                left: -10.0,
+                right: 300.0,
+                bottom: 200.0,
+                top: -10.0,
+            },
+            background: Color::BLACK,
+            gradients: Vec::new(),
+            shapes: vec![
+                Shape::Rect(RectData {
+                    position: [60.0, 20.0],
+                    size: [60.0, 20.0],
+                    border_radius: [4.0; 4],
+                    color: Color::rgba(40, 40, 90, 0.5),
+                    ..Default::default()
+                }),
+                Shape::Circle(CircleData {
+                    position: [200.0, 100.0],
+                    radius: 20.0,
+                    ..Default::default()
+                }),
+                Shape::Line {
+                    points: vec![[120.0, 20.0], [160.0, 20.0], [160.0, 100.0]],
+                    width: 2.0,
+                    color: Color::WHITE,
+                },
+                Shape::Text {
+                    content: "Aurora 3".to_string(),
+                    position: [8.0, 8.0],
+                    scale: 2.0,
+                    color: Color::WHITE,
+                },
+            ],
+        }
+    }
+
+    /// Byte offsets of the objects from the cross-reference table `startxref` points at
+    fn xref_offsets(file: &[u8]) -> Vec<usize> {
+        // Everything after the binary comment of the header is ASCII
+        let ascii = |bytes: &[u8]| std::str::from_utf8(bytes).unwrap().to_string();
+        let tail = ascii(&file[file.len().saturating_sub(64)..]);
+        let start: usize = tail
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|rest| rest.lines().next())
+            .and_then(|line| line.parse().ok())
+            .expect("No startxref");
+        let table = ascii(&file[start..]);
+        assert!(table.starts_with("xref\n"));
+
+        let mut lines = table.lines().skip(1);
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.strip_prefix("0 "))
+            .and_then(|count| count.parse().ok())
+            .expect("No subsection");
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+
+        lines
+            .take(count - 1)
+            .map(|line| {
+                // 20 bytes with the line break, as the format requires
+                assert_eq!(line.len(), 19, "{:?}", line);
+                assert!(line.ends_with(" 00000 n "), "{:?}", line);
+                line[..10].parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn xref_points_at_every_object() {
+        for layout in [PageLayout::Fit, PageLayout::Tiles { scale: 2.0 }] {
+            let options = PdfOptions {
+                paper: Paper::A4,
+                layout,
+                ..Default::default()
+            };
+            let file = drawing().to_pdf(&options);
+
+            let offsets = xref_offsets(&file);
+            assert!(!offsets.is_empty());
+            for (index, offset) in offsets.into_iter().enumerate() {
+                let header = format!("{} 0 obj\n", index + 1);
+                assert!(
+                    file[offset..].starts_with(header.as_bytes()),
+                    "object {} isn't at {}",
+                    index + 1,
+                    offset
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn writes_plain_numbers() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(-0.0001), "0");
+        assert_eq!(num(2.5), "2.5");
+        assert_eq!(num(1.0 / 3.0), "0.333");
+        assert_eq!(num(f32::NAN), "0");
+        assert_eq!(num(f32::INFINITY), "0");
+        assert_eq!(num(f32::NEG_INFINITY), "0");
+        assert!(!num(f32::MAX).contains(['e', 'i', 'N']));
+    }
+}
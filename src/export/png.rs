@@ -0,0 +1,329 @@
+use std::io::{self, Write};
+
+use miniz_oxide::deflate::core::{
+    compress_to_output, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+
+/// miniz level, the same trade of size for speed as zlib's default
+const LEVEL: i32 = 6;
+
+/// Writes an 8 bit RGBA PNG a few rows at a time, so images larger than memory can be
+/// exported in strips. The filtered rows go through the streaming zlib compressor of
+/// `miniz_oxide`, every strip ends up in its own IDAT chunk.
+pub struct PngEncoder<W: Write> {
+    writer: W,
+    width: u32,
+    rows_left: u32,
+    /// Unfiltered previous row, for the Up and Paeth filters
+    previous: Vec<u8>,
+    compressor: CompressorOxide,
+}
+
+impl<W: Write> PngEncoder<W> {
+    /// Writes the signature and the header of a `width` by `height` image
+    pub fn new(mut writer: W, width: u32, height: u32) -> io::Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PNG images need at least one pixel",
+            ));
+        }
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlacing
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+
+        // Positive window bits ask for the zlib wrapper PNG wants, with its Adler-32
+        let flags = create_comp_flags_from_zip_params(LEVEL, 15, 0);
+
+        Ok(Self {
+            writer,
+            width,
+            rows_left: height,
+            previous: vec![0; width as usize * 4],
+            compressor: CompressorOxide::new(flags),
+        })
+    }
+
+    /// Appends rows of tightly packed, straight alpha sRGB pixels
+    pub fn write_rows(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let stride = self.width as usize * 4;
+        let rows = (pixels.len() / stride) as u32;
+        if !pixels.len().is_multiple_of(stride) || rows > self.rows_left {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Pixels don't fill whole rows of the image",
+            ));
+        }
+        self.rows_left -= rows;
+
+        let mut filtered = Vec::with_capacity(pixels.len() + rows as usize);
+        for row in pixels.chunks_exact(stride) {
+            filter_row(row, &self.previous, &mut filtered);
+            self.previous.copy_from_slice(row);
+        }
+
+        let bytes = self.compress(&filtered, TDEFLFlush::None)?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        write_chunk(&mut self.writer, b"IDAT", &bytes)
+    }
+
+    /// Ends the image, every row has to be written by now
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows_left > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The image is missing rows",
+            ));
+        }
+
+        // Whatever the compressor still holds, the final block and the Adler-32
+        let bytes = self.compress(&[], TDEFLFlush::Finish)?;
+        write_chunk(&mut self.writer, b"IDAT", &bytes)?;
+        write_chunk(&mut self.writer, b"IEND", &[])?;
+
+        Ok(self.writer)
+    }
+
+    /// The compressed bytes the compressor lets go of, it keeps some back until `Finish`
+    fn compress(&mut self, data: &[u8], flush: TDEFLFlush) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let (status, _) = compress_to_output(&mut self.compressor, data, flush, |output| {
+            bytes.extend_from_slice(output);
+            true
+        });
+
+        match status {
+            TDEFLStatus::Okay | TDEFLStatus::Done => Ok(bytes),
+            _ => Err(io::Error::other(format!(
+                "Couldn't compress the image: {:?}",
+                status
+            ))),
+        }
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    writer.write_all(&crc(kind, data).to_be_bytes())
+}
+
+/// CRC-32 of a chunk, over its type and data
+fn crc(kind: &[u8; 4], data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Appends the filter type and the row filtered with whichever of the five filters gives the
+/// smallest sum of absolute differences, the heuristic the PNG spec suggests
+fn filter_row(row: &[u8], previous: &[u8], out: &mut Vec<u8>) {
+    let left = |i: usize| if i >= 4 { row[i - 4] } else { 0 };
+    let up_left = |i: usize| if i >= 4 { previous[i - 4] } else { 0 };
+
+    let mut best: Option<(u64, u8, Vec<u8>)> = None;
+    for kind in 0..5u8 {
+        let filtered: Vec<u8> = (0..row.len())
+            .map(|i| {
+                let predicted = match kind {
+                    0 => 0,
+                    1 => left(i),
+                    2 => previous[i],
+                    3 => ((left(i) as u16 + previous[i] as u16) / 2) as u8,
+                    _ => paeth(left(i), previous[i], up_left(i)),
+                };
+                row[i].wrapping_sub(predicted)
+            })
+            .collect();
+        let cost: u64 = filtered
+            .iter()
+            .map(|&byte| (byte as i8).unsigned_abs() as u64)
+            .sum();
+
+        if best
+            .as_ref()
+            .is_none_or(|(best_cost, ..)| cost < *best_cost)
+        {
+            best = Some((cost, kind, filtered));
+        }
+    }
+
+    if let Some((_, kind, filtered)) = best {
+        out.push(kind);
+        out.extend_from_slice(&filtered);
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noise with repeated stretches, so both literals and matches show up
+    fn pixels(width: u32, height: u32) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let pixel = if (x / 7 + y) % 3 == 0 {
+                    [40, 40, 90, 255]
+                } else {
+                    state.to_le_bytes()
+                };
+                pixels.extend_from_slice(&pixel);
+            }
+        }
+
+        pixels
+    }
+
+    /// Checks the chunks and returns the size and the unfiltered pixels
+    fn decode(file: &[u8]) -> (u32, u32, Vec<u8>) {
+        let rest = file
+            .strip_prefix(b"\x89PNG\r\n\x1a\n")
+            .expect("No signature");
+        let mut chunks = Vec::new();
+        let mut rest = rest;
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(super::crc(kind.try_into().unwrap(), data), crc);
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+
+        let (kind, header) = chunks[0];
+        assert_eq!(kind, b"IHDR");
+        assert_eq!(header[8..], [8, 6, 0, 0, 0]);
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        assert_eq!(chunks.last().map(|chunk| chunk.0), Some(&b"IEND"[..]));
+
+        let compressed: Vec<u8> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"IDAT")
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect();
+        let filtered = miniz_oxide::inflate::decompress_to_vec_zlib(&compressed).unwrap();
+
+        let stride = width as usize * 4;
+        assert_eq!(filtered.len(), (stride + 1) * height as usize);
+        let mut pixels: Vec<u8> = Vec::with_capacity(stride * height as usize);
+        for row in filtered.chunks_exact(stride + 1) {
+            let start = pixels.len();
+            for i in 0..stride {
+                let left = if i >= 4 { pixels[start + i - 4] } else { 0 };
+                let up = if start > 0 {
+                    pixels[start + i - stride]
+                } else {
+                    0
+                };
+                let up_left = if start > 0 && i >= 4 {
+                    pixels[start + i - stride - 4]
+                } else {
+                    0
+                };
+                let predicted = match row[0] {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    4 => paeth(left, up, up_left),
+                    kind => panic!("Unknown filter {}", kind),
+                };
+                pixels.push(row[1 + i].wrapping_add(predicted));
+            }
+        }
+
+        (width, height, pixels)
+    }
+
+    #[test]
+    fn decodes_to_the_written_pixels() {
+        let (width, height) = (70, 40);
+        let pixels = pixels(width, height);
+
+        let mut png = PngEncoder::new(Vec::new(), width, height).unwrap();
+        png.write_rows(&pixels).unwrap();
+        let file = png.finish().unwrap();
+
+        assert_eq!(decode(&file), (width, height, pixels));
+    }
+
+    #[test]
+    fn strips_decode_like_one_write() {
+        let (width, height) = (33, 20);
+        let pixels = pixels(width, height);
+        let stride = width as usize * 4;
+
+        let mut png = PngEncoder::new(Vec::new(), width, height).unwrap();
+        let mut rest = &pixels[..];
+        for rows in [1, 3, 0, 7, 9] {
+            let (strip, after) = rest.split_at(rows * stride);
+            png.write_rows(strip).unwrap();
+            rest = after;
+        }
+        let file = png.finish().unwrap();
+
+        assert_eq!(decode(&file), (width, height, pixels));
+    }
+
+    #[test]
+    fn compresses_long_runs() {
+        let (width, height) = (1000, 100);
+        let pixels = [10, 20, 30, 255].repeat((width * height) as usize);
+
+        let mut png = PngEncoder::new(Vec::new(), width, height).unwrap();
+        png.write_rows(&pixels).unwrap();
+        let file = png.finish().unwrap();
+
+        assert!(file.len() < pixels.len() / 50);
+        assert_eq!(decode(&file), (width, height, pixels));
+    }
+
+    #[test]
+    fn rejects_wrong_row_counts() {
+        assert!(PngEncoder::new(Vec::new(), 0, 10).is_err());
+        assert!(PngEncoder::new(Vec::new(), 10, 0).is_err());
+
+        let mut png = PngEncoder::new(Vec::new(), 4, 2).unwrap();
+        // Half a row, and three rows into an image of two
+        assert!(png.write_rows(&[0; 8]).is_err());
+        assert!(png.write_rows(&[0; 48]).is_err());
+
+        png.write_rows(&[0; 16]).unwrap();
+        assert_eq!(
+            png.finish().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,466 @@
+use crate::color::Color;
+use crate::gradient::GradientKind;
+use crate::scene_file::{CircleData, RectData};
+use crate::text::{Text, GLYPH_HEIGHT};
+
+use super::{num, Drawing, Shape};
+
+impl Drawing {
+    /// SVG document of the drawing, one unit per world unit. Rects with different corner
+    /// radii become paths, borders are strokes inside the outline like on screen.
+    pub fn to_svg(&self) -> String {
+        let mut svg = Svg {
+            drawing: self,
+            defs: String::new(),
+            body: String::new(),
+            ids: 0,
+        };
+
+        svg.body.push_str(&format!(
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}/>\n",
+            num(self.bounds.left),
+            num(self.bounds.top),
+            num(self.width()),
+            num(self.height()),
+            paint("fill", self.background),
+        ));
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect(rect) => svg.rect(rect),
+                Shape::Circle(circle) => svg.circle(circle),
+                Shape::Line {
+                    points,
+                    width,
+                    color,
+                } => svg.line(points, *width, *color),
+                Shape::Text {
+                    content,
+                    position,
+                    scale,
+                    color,
+                } => svg.text(content, *position, *scale, *color),
+            }
+        }
+
+        let mut document = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" \
+             width=\"{}\" height=\"{}\">\n",
+            num(self.bounds.left),
+            num(self.bounds.top),
+            num(self.width()),
+            num(self.height()),
+            num(self.width()),
+            num(self.height()),
+        );
+        if !svg.defs.is_empty() {
+            document.push_str(&format!("  <defs>\n{}  </defs>\n", svg.defs));
+        }
+        document.push_str(&svg.body);
+        document.push_str("</svg>\n");
+
+        document
+    }
+}
+
+struct Svg<'a> {
+    drawing: &'a Drawing,
+    /// Gradients and shadow filters, each used by one shape
+    defs: String,
+    body: String,
+    ids: u32,
+}
+
+impl Svg<'_> {
+    fn rect(&mut self, rect: &RectData) {
+        let [x, y] = rect.top_left();
+        let [width, height] = rect.full_size();
+        let half = width.min(height) / 2.0;
+        let radii = rect.border_radius.map(|radius| radius.min(half));
+
+        let pivot = [x + rect.pivot[0] * width, y + rect.pivot[1] * height];
+        let transform = if rect.rotation != 0.0 {
+            format!(
+                " transform=\"rotate({} {} {})\"",
+                num(rect.rotation.to_degrees()),
+                num(pivot[0]),
+                num(pivot[1])
+            )
+        } else {
+            String::new()
+        };
+
+        let shadow = self.shadow(
+            rect.shadow_offset,
+            rect.shadow_blur,
+            rect.shadow_color,
+            rect.rotation,
+        );
+        let fill = self.fill(rect.fill_gradient, rect.color, [x, y], [width, height]);
+
+        // The stroke is centered on the outline, so it runs half the border inside of it
+        let inset = rect.border / 2.0;
+        let stroke = self.stroke(
+            rect.border,
+            rect.border_gradient,
+            rect.border_color,
+            [x, y],
+            [width, height],
+        );
+        let (x, y) = (x + inset, y + inset);
+        let (width, height) = (
+            (width - rect.border).max(0.0),
+            (height - rect.border).max(0.0),
+        );
+        let radii = radii.map(|radius| (radius - inset).max(0.0));
+
+        let outline = if radii.iter().all(|&radius| radius == radii[0]) {
+            let rounded = if radii[0] > 0.0 {
+                format!(" rx=\"{}\"", num(radii[0]))
+            } else {
+                String::new()
+            };
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}",
+                num(x),
+                num(y),
+                num(width),
+                num(height),
+                rounded
+            )
+        } else {
+            // Corners clockwise from the top left, like `border_radius`
+            let [top_left, top_right, bottom_right, bottom_left] = radii;
+            let (right, bottom) = (x + width, y + height);
+            format!(
+                "<path d=\"M {} {} H {} A {r1} {r1} 0 0 1 {} {} V {} A {r2} {r2} 0 0 1 {} {} \
+                 H {} A {r3} {r3} 0 0 1 {} {} V {} A {r0} {r0} 0 0 1 {} {} Z\"",
+                num(x + top_left),
+                num(y),
+                num(right - top_right),
+                num(right),
+                num(y + top_right),
+                num(bottom - bottom_right),
+                num(right - bottom_right),
+                num(bottom),
+                num(x + bottom_left),
+                num(x),
+                num(bottom - bottom_left),
+                num(y + top_left),
+                num(x + top_left),
+                num(y),
+                r0 = num(top_left),
+                r1 = num(top_right),
+                r2 = num(bottom_right),
+                r3 = num(bottom_left),
+            )
+        };
+
+        self.body.push_str(&format!(
+            "  {}{}{}{}{}/>\n",
+            outline, fill, stroke, shadow, transform
+        ));
+    }
+
+    fn circle(&mut self, circle: &CircleData) {
+        let [x, y] = circle.position;
+        let size = [circle.radius * 2.0; 2];
+        let corner = [x - circle.radius, y - circle.radius];
+
+        let shadow = self.shadow(
+            circle.shadow_offset,
+            circle.shadow_blur,
+            circle.shadow_color,
+            0.0,
+        );
+        let fill = self.fill(circle.fill_gradient, circle.color, corner, size);
+        let stroke = self.stroke(
+            circle.border,
+            circle.border_gradient,
+            circle.border_color,
+            corner,
+            size,
+        );
+        let radius = (circle.radius - circle.border / 2.0).max(0.0);
+
+        self.body.push_str(&format!(
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\"{}{}{}/>\n",
+            num(x),
+            num(y),
+            num(radius),
+            fill,
+            stroke,
+            shadow
+        ));
+    }
+
+    fn line(&mut self, points: &[[f32; 2]], width: f32, color: Color) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|[x, y]| format!("{},{}", num(*x), num(*y)))
+            .collect();
+
+        self.body.push_str(&format!(
+            "  <polyline points=\"{}\" fill=\"none\"{} stroke-width=\"{}\" \
+             stroke-linejoin=\"miter\"/>\n",
+            points.join(" "),
+            paint("stroke", color),
+            num(width)
+        ));
+    }
+
+    /// A monospace font stretched to the width of the bitmap font, so the layout stays
+    fn text(&mut self, content: &str, position: [f32; 2], scale: f32, color: Color) {
+        let text = Text::new(content, position, scale, [0.0; 4]);
+        // The last row of a glyph is below the baseline
+        let baseline = position[1] + (GLYPH_HEIGHT - 1) as f32 * scale;
+
+        self.body.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" \
+             textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"{}>{}</text>\n",
+            num(position[0]),
+            num(baseline),
+            num(GLYPH_HEIGHT as f32 * scale),
+            num(text.size()[0]),
+            paint("fill", color),
+            escape(content)
+        ));
+    }
+
+    /// Fill attributes, a gradient is mapped onto the box at `position` of `size`
+    fn fill(&mut self, gradient: u32, color: Color, position: [f32; 2], size: [f32; 2]) -> String {
+        match self.gradient(gradient, position, size) {
+            Some(id) => format!(" fill=\"url(#{})\"", id),
+            None => paint("fill", color),
+        }
+    }
+
+    fn stroke(
+        &mut self,
+        width: f32,
+        gradient: u32,
+        color: Color,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) -> String {
+        if width <= 0.0 {
+            return String::new();
+        }
+
+        let paint = match self.gradient(gradient, position, size) {
+            Some(id) => format!(" stroke=\"url(#{})\"", id),
+            None => paint("stroke", color),
+        };
+        format!("{} stroke-width=\"{}\"", paint, num(width))
+    }
+
+    /// Defines gradient `id` for one shape and returns the id of the definition. Linear
+    /// gradients span the shape along their angle like in `gradient.wgsl`.
+    fn gradient(&mut self, id: u32, position: [f32; 2], size: [f32; 2]) -> Option<String> {
+        let gradient = self.drawing.gradient(id)?;
+        let def = self.next_id("gradient");
+
+        let stops: String = gradient
+            .stops
+            .iter()
+            .map(|(offset, color)| {
+                let opacity = if color.a < 1.0 {
+                    format!(" stop-opacity=\"{}\"", num(color.a))
+                } else {
+                    String::new()
+                };
+                format!(
+                    "      <stop offset=\"{}\" stop-color=\"{}\"{}/>\n",
+                    num(*offset),
+                    hex(*color),
+                    opacity
+                )
+            })
+            .collect();
+
+        let element = match gradient.kind {
+            GradientKind::Linear { angle } => {
+                let direction = [angle.cos(), angle.sin()];
+                let extent = (size[0] * direction[0]).abs() + (size[1] * direction[1]).abs();
+                let center = [position[0] + size[0] / 2.0, position[1] + size[1] / 2.0];
+                let half = [direction[0] * extent / 2.0, direction[1] * extent / 2.0];
+                format!(
+                    "    <linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                     x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">\n{}    </linearGradient>\n",
+                    def,
+                    num(center[0] - half[0]),
+                    num(center[1] - half[1]),
+                    num(center[0] + half[0]),
+                    num(center[1] + half[1]),
+                    stops
+                )
+            }
+            // Relative to the bounding box, which stretches it into an ellipse like on screen
+            GradientKind::Radial { center, radius } => format!(
+                "    <radialGradient id=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\">\n{}    \
+                 </radialGradient>\n",
+                def,
+                num(center[0]),
+                num(center[1]),
+                num(radius / 2.0),
+                stops
+            ),
+        };
+        self.defs.push_str(&element);
+
+        Some(def)
+    }
+
+    /// Filter attribute for a drop shadow, the spread has no equivalent. `rotation` is undone
+    /// since the filter is applied before the transform of the shape.
+    fn shadow(&mut self, offset: [f32; 2], blur: f32, color: Color, rotation: f32) -> String {
+        if color.a <= 0.0 {
+            return String::new();
+        }
+
+        let (sin, cos) = (-rotation).sin_cos();
+        let dx = cos * offset[0] - sin * offset[1];
+        let dy = sin * offset[0] + cos * offset[1];
+
+        let id = self.next_id("shadow");
+        self.defs.push_str(&format!(
+            "    <filter id=\"{}\" x=\"-50%\" y=\"-50%\" width=\"200%\" height=\"200%\">\n      \
+             <feDropShadow dx=\"{}\" dy=\"{}\" stdDeviation=\"{}\" flood-color=\"{}\" \
+             flood-opacity=\"{}\"/>\n    </filter>\n",
+            id,
+            num(dx),
+            num(dy),
+            num(blur / 2.0),
+            hex(color),
+            num(color.a)
+        ));
+
+        format!(" filter=\"url(#{})\"", id)
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.ids += 1;
+        format!("{}-{}", prefix, self.ids)
+    }
+}
+
+/// `fill` or `stroke` with its opacity, which is left out when opaque
+fn paint(attribute: &str, color: Color) -> String {
+    let mut paint = format!(" {}=\"{}\"", attribute, hex(color));
+    if color.a < 1.0 {
+        paint.push_str(&format!(" {}-opacity=\"{}\"", attribute, num(color.a)));
+    }
+
+    paint
+}
+
+fn hex(color: Color) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Limits;
+
+    fn drawing(rect: RectData) -> Drawing {
+        Drawing {
+            bounds: Limits {
+                left: 0.0,
+                right: 100.0,
+                bottom: 100.0,
+                top: 0.0,
+            },
+            background: Color::BLACK,
+            gradients: Vec::new(),
+            shapes: vec![Shape::Rect(rect)],
+        }
+    }
+
+    #[test]
+    fn leaves_out_what_svg_cant_read() {
+        // No NaN, infinity or negative zero in attributes
+        assert_eq!(num(f32::NAN), "0");
+        assert_eq!(num(f32::INFINITY), "0");
+        assert_eq!(num(f32::NEG_INFINITY), "0");
+        assert_eq!(num(-0.0), "0");
+        assert_eq!(num(-0.0001), "0");
+        assert_eq!(num(-1.5), "-1.5");
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape("Tom & Jerry <3 >_<"),
+            "Tom &amp; Jerry &lt;3 &gt;_&lt;"
+        );
+        assert_eq!(escape("Aurora"), "Aurora");
+    }
+
+    #[test]
+    fn draws_different_corners_as_a_path() {
+        let rect = RectData {
+            position: [50.0, 50.0],
+            size: [20.0, 10.0],
+            color: Color::WHITE,
+            ..Default::default()
+        };
+        let svg = drawing(RectData {
+            border_radius: [4.0; 4],
+            ..rect
+        })
+        .to_svg();
+        assert!(svg.contains("<rect x=\"30\" y=\"40\" width=\"40\" height=\"20\" rx=\"4\""));
+        assert!(!svg.contains("<path"));
+
+        let svg = drawing(RectData {
+            border_radius: [4.0, 0.0, 8.0, 0.0],
+            ..rect
+        })
+        .to_svg();
+        assert!(svg.contains("<path d=\"M 34 40 H 70 "));
+        assert!(svg.contains("A 8 8 0 0 1 62 60"));
+    }
+
+    #[test]
+    fn rotates_about_the_pivot() {
+        let svg = drawing(RectData {
+            position: [50.0, 50.0],
+            size: [20.0, 10.0],
+            rotation: std::f32::consts::FRAC_PI_2,
+            pivot: [0.0, 1.0],
+            ..Default::default()
+        })
+        .to_svg();
+        // The bottom left corner
+        assert!(svg.contains(" transform=\"rotate(90 30 60)\""));
+    }
+
+    #[test]
+    fn turns_the_shadow_back() {
+        let svg = drawing(RectData {
+            position: [50.0, 50.0],
+            size: [20.0, 10.0],
+            rotation: std::f32::consts::FRAC_PI_2,
+            shadow_offset: [10.0, 0.0],
+            shadow_blur: 4.0,
+            shadow_color: Color::BLACK,
+            ..Default::default()
+        })
+        .to_svg();
+        // The filter runs before the rotation, which turns this back to [10, 0]
+        assert!(svg.contains("<feDropShadow dx=\"0\" dy=\"-10\" stdDeviation=\"2\""));
+        assert!(svg.contains(" filter=\"url(#shadow-1)\""));
+    }
+}
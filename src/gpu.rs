@@ -0,0 +1,197 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use winit::window::Window;
+
+/// Backends tried in order, the secondary ones (GL, DX11) only if no primary adapter works
+#[cfg(not(target_arch = "wasm32"))]
+const BACKENDS: [wgpu::Backends; 2] = [wgpu::Backends::PRIMARY, wgpu::Backends::SECONDARY];
+#[cfg(target_arch = "wasm32")]
+const BACKENDS: [wgpu::Backends; 1] = [wgpu::Backends::all()];
+
+/// Why no device could be created, shown in the window instead of drawing
+#[derive(Debug)]
+pub enum InitError {
+    /// No backend has an adapter that can draw into the window
+    NoAdapter,
+    /// Every adapter refused to create a device, even with the lowest limits
+    NoDevice(wgpu::RequestDeviceError),
+    /// The adapter can't render to any texture format of the surface
+    NoSurfaceFormat { adapter: String },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::NoAdapter => write!(f, "No graphics adapter supports drawing here"),
+            InitError::NoDevice(err) => write!(f, "The graphics adapter failed to start: {}", err),
+            InitError::NoSurfaceFormat { adapter } => {
+                write!(f, "{} can't draw into this window", adapter)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::NoDevice(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Device shared by every window of the process, each `State` only has its own surface.
+///
+/// On the web every canvas needs its own WebGL context, so there each viewer has its own.
+pub struct Gpu {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    /// Set from the error handler of the device, which may run on another thread
+    lost: Arc<AtomicBool>,
+    /// A surface reported `Lost` or `OutOfMemory` and no frame got through since, meanwhile
+    /// errors that mention a loss in any wording count as one
+    surface_failed: Arc<AtomicBool>,
+}
+
+impl Gpu {
+    /// Picks an adapter that can draw into `window` and returns the surface it was picked for.
+    /// Falls back to the secondary backends and to software adapters before giving up.
+    pub async fn new(window: &Window) -> Result<(Self, wgpu::Surface), InitError> {
+        let mut error = InitError::NoAdapter;
+
+        for backends in BACKENDS {
+            for force_fallback_adapter in [false, true] {
+                // A surface belongs to the instance it was created with
+                let instance = wgpu::Instance::new(backends);
+                let surface = unsafe { instance.create_surface(window) };
+                let adapter = instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::default(),
+                        compatible_surface: Some(&surface),
+                        force_fallback_adapter,
+                    })
+                    .await;
+                let Some(adapter) = adapter else {
+                    continue;
+                };
+
+                match Self::request_device(&adapter).await {
+                    Ok((device, queue)) => {
+                        let lost = Arc::new(AtomicBool::new(false));
+                        let surface_failed = Arc::new(AtomicBool::new(false));
+                        device.on_uncaptured_error(on_error(
+                            Arc::clone(&lost),
+                            Arc::clone(&surface_failed),
+                        ));
+                        let gpu = Self {
+                            instance,
+                            adapter,
+                            device,
+                            queue,
+                            lost,
+                            surface_failed,
+                        };
+                        return Ok((gpu, surface));
+                    }
+                    Err(err) => {
+                        log::warn!("{} failed to create a device", adapter.get_info().name);
+                        error = InitError::NoDevice(err);
+                    }
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Asks for the best limits first and settles for the ones of WebGL2
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+    ) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+        let mut limits = vec![
+            wgpu::Limits::downlevel_defaults(),
+            wgpu::Limits::downlevel_webgl2_defaults(),
+        ];
+        if cfg!(not(target_arch = "wasm32")) {
+            limits.insert(0, wgpu::Limits::default());
+        }
+
+        let mut result = Err(wgpu::RequestDeviceError);
+        for limits in limits {
+            result = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        // Still allow surfaces as large as the adapter can handle
+                        limits: limits.using_resolution(adapter.limits()),
+                        label: None,
+                    },
+                    None,
+                )
+                .await;
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Surface of another window, drawn with the same device
+    pub fn create_surface(&self, window: &Window) -> wgpu::Surface {
+        unsafe { self.instance.create_surface(window) }
+    }
+
+    /// The driver reset or removed the device, nothing drawn with it shows up anymore.
+    /// Every state using it has to be recreated, see `State::recover`.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// For losses only noticed through the surface, which wgpu doesn't report as errors
+    pub fn mark_lost(&self) {
+        self.lost.store(true, Ordering::Relaxed);
+    }
+
+    /// A surface reported `Lost` or `OutOfMemory`, so the device may be gone as well
+    pub fn mark_surface_failed(&self) {
+        self.surface_failed.store(true, Ordering::Relaxed);
+    }
+
+    /// A frame was presented after all, errors go back to panicking unless they are a loss
+    pub fn clear_surface_failed(&self) {
+        self.surface_failed.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Marks the device lost on errors that come from losing it. Anything else is a bug,
+/// so it panics like the default handler.
+///
+/// wgpu 0.12 has no device lost callback and reports the loss as a validation error. Only
+/// its wording, "device is lost", tells it apart from the others. Between a failed surface
+/// and the next presented frame any mention of "lost" is enough, in case other versions or
+/// backends word it differently.
+fn on_error(
+    lost: Arc<AtomicBool>,
+    surface_failed: Arc<AtomicBool>,
+) -> impl Fn(wgpu::Error) + Send + 'static {
+    move |error| {
+        let device_lost = match &error {
+            wgpu::Error::OutOfMemory { .. } => true,
+            wgpu::Error::Validation { description, .. } => {
+                description.contains("device is lost")
+                    || surface_failed.load(Ordering::Relaxed)
+                        && description.to_lowercase().contains("lost")
+            }
+        };
+        if !device_lost {
+            panic!("wgpu error: {}", error);
+        }
+
+        log::error!("Lost the graphics device: {}", error);
+        lost.store(true, Ordering::Relaxed);
+    }
+}
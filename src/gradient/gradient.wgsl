@@ -0,0 +1,45 @@
+struct Gradient {
+    // Kind (1 linear, 2 radial) and stop count
+    header: vec4<u32>;
+    params: vec4<f32>;
+    offsets: array<vec4<f32>, 2>;
+    colors: array<vec4<f32>, 8>;
+};
+
+struct Gradients {
+    items: array<Gradient, 32>;
+};
+
+[[group(1), binding(0)]]
+var<uniform> gradients: Gradients;
+
+// Samples gradient `id` (1 based) at `local`, a position relative to the center of a shape of `size`
+fn gradient_color(id: u32, local: vec2<f32>, size: vec2<f32>) -> vec4<f32> {
+    // Indexing goes through the uniform directly, dynamic indices into a copy aren't allowed
+    let index = id - 1u;
+    let header = gradients.items[index].header;
+    let params = gradients.items[index].params;
+
+    var t: f32;
+    if (header.x == 1u) {
+        let dir = vec2<f32>(cos(params.x), sin(params.x));
+        // Length of the gradient line, so the first and last stop touch the corners
+        let extent = abs(size.x * dir.x) + abs(size.y * dir.y);
+        t = dot(local, dir) / max(extent, 0.0001) + 0.5;
+    } else {
+        let uv = local / size + 0.5;
+        t = length((uv - params.xy) * 2.0) / max(params.z, 0.0001);
+    }
+    t = clamp(t, 0.0, 1.0);
+
+    var color = gradients.items[index].colors[0];
+    for (var i: u32 = 1u; i < header.y; i = i + 1u) {
+        let start = gradients.items[index].offsets[(i - 1u) / 4u][(i - 1u) % 4u];
+        let end = gradients.items[index].offsets[i / 4u][i % 4u];
+        let amount = clamp((t - start) / max(end - start, 0.0001), 0.0, 1.0);
+        color = mix(color, gradients.items[index].colors[i], amount);
+    }
+
+    return color;
+}
+
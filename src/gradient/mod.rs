@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, TextureFormat};
+
+use crate::color::Color;
+
+/// Has to match the array sizes in `gradient.wgsl`
+pub const MAX_GRADIENTS: usize = 32;
+pub const MAX_STOPS: usize = 8;
+
+/// Index into the gradient table, stored in the instance data. 0 means a flat color.
+pub type GradientId = u32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GradientKind {
+    /// Angle in radians, 0 runs from left to right
+    Linear { angle: f32 },
+    /// Center relative to the shape, (0.5, 0.5) is the middle. A radius of 1 reaches the edges.
+    Radial { center: [f32; 2], radius: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    /// Offsets in 0..1 with their colors, sorted by offset
+    pub stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    pub fn linear(angle: f32, stops: &[(f32, Color)]) -> Self {
+        Self {
+            kind: GradientKind::Linear { angle },
+            stops: stops.to_vec(),
+        }
+    }
+
+    pub fn radial(center: [f32; 2], radius: f32, stops: &[(f32, Color)]) -> Self {
+        Self {
+            kind: GradientKind::Radial { center, radius },
+            stops: stops.to_vec(),
+        }
+    }
+
+    /// Spreads the colors evenly between 0 and 1
+    pub fn evenly_spaced(kind: GradientKind, colors: &[Color]) -> Self {
+        let step = 1.0 / (colors.len().max(2) - 1) as f32;
+
+        Self {
+            kind,
+            stops: colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| (i as f32 * step, *color))
+                .collect(),
+        }
+    }
+
+    fn to_uniform(&self, format: TextureFormat) -> GradientUniform {
+        let mut uniform = GradientUniform::default();
+
+        match self.kind {
+            GradientKind::Linear { angle } => {
+                uniform.header[0] = 1;
+                uniform.params = [angle, 0.0, 0.0, 0.0];
+            }
+            GradientKind::Radial { center, radius } => {
+                uniform.header[0] = 2;
+                uniform.params = [center[0], center[1], radius, 0.0];
+            }
+        }
+
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if stops.len() > MAX_STOPS {
+            log::warn!(
+                "Gradient has {} stops, only the first {} are used",
+                stops.len(),
+                MAX_STOPS
+            );
+        }
+
+        for (i, (offset, color)) in stops.iter().take(MAX_STOPS).enumerate() {
+            uniform.offsets[i / 4][i % 4] = *offset;
+            uniform.colors[i] = color.for_format(format);
+        }
+        uniform.header[1] = stops.len().min(MAX_STOPS) as u32;
+
+        uniform
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GradientUniform {
+    /// Kind (1 linear, 2 radial) and stop count
+    header: [u32; 4],
+    params: [f32; 4],
+    offsets: [[f32; 4]; MAX_STOPS / 4],
+    colors: [[f32; 4]; MAX_STOPS],
+}
+
+/// Gradient table shared by all pipelines, bound as group 1.
+///
+/// Uses a uniform buffer instead of a storage buffer since WebGL2 has no storage buffers.
+pub struct Gradients {
+    gradients: Vec<Gradient>,
+    format: TextureFormat,
+    buffer: Buffer,
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+}
+
+impl Gradients {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gradient Buffer"),
+            contents: bytemuck::cast_slice(&[GradientUniform::default(); MAX_GRADIENTS]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("gradient_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("gradient_bind_group"),
+        });
+
+        Self {
+            gradients: Vec::new(),
+            format,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Adds a gradient to the table, `None` if it is full
+    pub fn add(&mut self, queue: &Queue, gradient: Gradient) -> Option<GradientId> {
+        if self.gradients.len() >= MAX_GRADIENTS {
+            log::warn!("Gradient table is full ({} gradients)", MAX_GRADIENTS);
+            return None;
+        }
+
+        self.gradients.push(gradient);
+        let index = self.gradients.len() - 1;
+        self.write(queue, index);
+
+        Some(index as GradientId + 1)
+    }
+
+    /// Replaces a gradient, every shape using its id picks up the change on the next frame.
+    /// `false` if there is no gradient with that id.
+    pub fn set(&mut self, queue: &Queue, id: GradientId, gradient: Gradient) -> bool {
+        let slot = match (id as usize)
+            .checked_sub(1)
+            .and_then(|index| self.gradients.get_mut(index))
+        {
+            Some(slot) => slot,
+            None => return false,
+        };
+        *slot = gradient;
+        self.write(queue, id as usize - 1);
+
+        true
+    }
+
+    pub fn get(&self, id: GradientId) -> Option<&Gradient> {
+        self.gradients.get((id as usize).checked_sub(1)?)
+    }
+
+    /// Every gradient in the table, the first one has id 1
+    pub fn iter(&self) -> impl Iterator<Item = &Gradient> {
+        self.gradients.iter()
+    }
+
+    /// Removes every gradient and zeroes the table, so shapes still holding an old id draw
+    /// nothing instead of the gradient that used to have it
+    pub fn clear(&mut self, queue: &Queue) {
+        self.gradients.clear();
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[GradientUniform::default(); MAX_GRADIENTS]),
+        );
+    }
+
+    fn write(&self, queue: &Queue, index: usize) {
+        let uniform = self.gradients[index].to_uniform(self.format);
+
+        queue.write_buffer(
+            &self.buffer,
+            (index * std::mem::size_of::<GradientUniform>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+}
@@ -1,99 +1,304 @@
-use winit::{
-    event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
+use std::rc::Rc;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Window, WindowId};
 
+mod animation;
+mod bracket;
 mod camera;
 mod circle;
+mod clock;
+mod color;
+mod compositor;
+#[cfg(not(target_arch = "wasm32"))]
+mod error_window;
+mod export;
+mod gpu;
+mod gradient;
+mod overlay;
+mod popover;
 mod rect;
+mod scene_file;
 mod state;
+mod text;
+mod theme;
+mod ui;
+#[cfg(target_arch = "wasm32")]
+mod viewer;
+mod views;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
-pub async fn run() {
+// What an embedder needs to drive a `State`, the modules themselves stay private
+pub use animation::{
+    Animation, AnimationId, Animator, Easing, Property, Repeat, Target, Tween, Value,
+};
+pub use bracket::{
+    Bracket, BracketView, ConnectorLine, Label, MatchData, MatchId, ParticipantId, Tournament,
+};
+pub use camera::{Camera, Space};
+pub use circle::Circle;
+pub use clock::{FrameClock, FrameStats, Tick};
+pub use color::Color;
+pub use compositor::{Filter, Group};
+pub use export::{Drawing, PageLayout, Paper, PdfOptions, PngEncoder, Region};
+pub use gpu::{Gpu, InitError};
+pub use gradient::{Gradient, GradientId, GradientKind};
+pub use rect::Rect;
+pub use scene_file::{SceneError, SceneFile};
+pub use state::{State, ViewerEvent};
+pub use theme::{Role, Roles, Theme};
+
+use views::{View, Views};
+
+/// Events sent to the event loop from outside of winit
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+enum UserEvent {
+    /// The browser entered or left fullscreen, the canvas needs a new size
+    FullscreenChanged,
+    /// The element around the canvas of the window changed size
+    CanvasResized(WindowId),
+    /// The device pixel ratio changed, so the same element has another size in physical pixels
+    PixelRatioChanged,
+    /// The state was changed from outside of the event loop, e.g. through the JavaScript API
+    StateChanged,
+    /// Windows were requested, they can only be created inside the running event loop
+    CreateWindows,
+}
+
+/// Logs to the console on the web, can be called more than once
+fn init_logging() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-            console_log::init_with_level(log::Level::Warn).expect("Could't initialize logger");
+            let _ = console_log::init_with_level(log::Level::Warn);
         } else {
-            env_logger::init();
+            let _ = env_logger::try_init();
         }
     }
+}
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-
-    #[cfg(target_arch = "wasm32")]
-    {
-        // Winit prevents sizing with CSS, so we have to set
-        // the size manually when on web.
-        use winit::dpi::PhysicalSize;
-        window.set_inner_size(PhysicalSize::new(3200, 1700));
-
-        use winit::platform::web::WindowExtWebSys;
-        web_sys::window()
-            .and_then(|win| win.document())
-            .and_then(|doc| {
-                let dst = doc.get_element_by_id("wgpu-sandbox")?;
-                let canvas = web_sys::Element::from(window.canvas());
-                dst.append_child(&canvas).ok()?;
-                Some(())
-            })
-            .expect("Couldn't append canvas to document body.");
-    }
+/// Opens a window with an example tournament. Natively `--windows <count>` opens several
+/// that share one device, `--scene <file>` shows a saved scene instead of the example,
+/// `--theme <name or file>` picks a built-in theme or loads one and
+/// `--export-svg <file>` saves all of it as SVG. `--export-pdf <file>` prints it onto one
+/// page of `--paper <a0..a4>`, A2 by default. `--export-png <file>` renders it at
+/// `--png-size <width>x<height>`, four times the window by default. On the web the canvas
+/// goes into the element with id `wgpu-sandbox`.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn run() {
+    init_logging();
 
-    let mut state = state::State::new(&window).await;
+    let event_loop = EventLoop::<UserEvent>::with_user_event();
 
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::WindowEvent {
-            ref event,
-            window_id,
-        } if window_id == window.id() => {
-            if !state.input(event) {
-                match event {
-                    WindowEvent::Resized(physical_size) => {
-                        state.resize(*physical_size);
-                    }
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let window = web::create_window("wgpu-sandbox", &event_loop)
+                .expect("Couldn't add the canvas to the document.");
+            // The viewer stays for as long as the page
+            let proxy = event_loop.create_proxy();
+            std::mem::forget(web::observe_size(&window, proxy.clone()));
+            std::mem::forget(web::observe_page(proxy));
+            let windows = vec![window];
+            let scene: Option<SceneFile> = None;
+            let theme: Option<Theme> = None;
+        } else {
+            let count = arg("--windows")
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(1);
+            let windows: Vec<Window> = (0..count.max(1))
+                .map(|_| winit::window::WindowBuilder::new().build(&event_loop).unwrap())
+                .collect();
 
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        // new_inner_size is &&mut so we have to dereference it twice
-                        state.resize(**new_inner_size);
+            let theme = arg("--theme").and_then(|name| {
+                if let Some(theme) = Theme::named(&name) {
+                    return Some(theme);
+                }
+                match Theme::load(&name) {
+                    Ok(theme) => Some(theme),
+                    Err(err) => {
+                        log::error!("Couldn't open the theme {}: {}", name, err);
+                        None
                     }
+                }
+            });
 
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
-                    _ => {}
+            let scene = arg("--scene").and_then(|path| match SceneFile::load(&path) {
+                Ok(scene) => Some(scene),
+                Err(err) => {
+                    log::error!("Couldn't open the scene {}: {}", path, err);
+                    None
                 }
-            }
+            });
         }
+    }
 
-        Event::RedrawRequested(window_id) if window_id == window.id() => {
-            state.update();
-            match state.render() {
-                Ok(_) => {}
-                // Reconfigure the surface if lost
-                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-                // The system is out of memory, we should probably quit
-                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                // All other errors (Outdated, Timeout) should be resolved by the next frame
-                Err(e) => eprintln!("{:?}", e),
+    let mut views = Views::default();
+    // Windows without a device only show why, until they're closed
+    let mut failed = Vec::new();
+    for window in windows {
+        let state = match views.iter_mut().next() {
+            Some(first) => State::with_gpu(&window, Rc::clone(first.state.gpu())),
+            None => State::new(&window).await,
+        };
+        match state {
+            Ok(mut state) => {
+                let loaded = scene.as_ref().map(|scene| state.load_scene(scene));
+                match loaded {
+                    Some(Ok(())) => {}
+                    Some(Err(err)) => {
+                        log::error!("Couldn't show the scene: {}", err);
+                        state.set_bracket(example_bracket());
+                    }
+                    None => state.set_bracket(example_bracket()),
+                }
+                if let Some(theme) = theme {
+                    state.set_theme(theme);
+                }
+                views.add(View { state, window });
+            }
+            Err(err) => {
+                show_error(&window, &err);
+                #[cfg(not(target_arch = "wasm32"))]
+                let window = error_window::ErrorWindow::new(window, &err);
+                failed.push(window);
             }
         }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    export(&mut views);
 
-        Event::MainEventsCleared => {
-            window.request_redraw();
+    event_loop.run(move |event, _, control_flow| {
+        if let Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            window_id,
+        } = event
+        {
+            failed.retain(|window| window.id() != window_id);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        for window in &mut failed {
+            window.handle_event(&event);
+        }
+        views.handle_event(event, control_flow);
+
+        // Nothing embeds the example, so nobody listens to clicks and hovers
+        for view in views.iter_mut() {
+            view.state.take_events();
+        }
+        if views.is_empty() && failed.is_empty() {
+            *control_flow = ControlFlow::Exit;
         }
-        _ => {}
     });
 }
+
+/// Logs why nothing can be drawn and shows it where the bracket would be. On the web the
+/// message replaces the canvas. A native window also gets it as its title, the message
+/// inside of it is drawn by `ErrorWindow` without the device.
+fn show_error(window: &Window, err: &InitError) {
+    log::error!("Couldn't start drawing: {}", err);
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            web::show_error(window, &err.to_string());
+        } else {
+            window.set_title(&format!("Couldn't start drawing: {}", err));
+        }
+    }
+}
+
+/// Writes everything the first window shows to the files given with `--export-svg`,
+/// `--export-pdf` and `--export-png`
+#[cfg(not(target_arch = "wasm32"))]
+fn export(views: &mut Views) {
+    let Some(view) = views.iter_mut().next() else {
+        return;
+    };
+    let drawing = view.state.drawing(export::Region::Content);
+
+    let mut files = Vec::new();
+    if let Some(path) = arg("--export-svg") {
+        files.push((path, drawing.to_svg().into_bytes()));
+    }
+    if let Some(path) = arg("--export-pdf") {
+        let paper = arg("--paper").map(|name| {
+            export::Paper::from_name(&name).unwrap_or_else(|| {
+                log::warn!("Unknown paper size {}, using A2", name);
+                export::Paper::A2
+            })
+        });
+        let options = export::PdfOptions {
+            paper: paper.unwrap_or(export::Paper::A2),
+            ..Default::default()
+        };
+        files.push((path, drawing.to_pdf(&options)));
+    }
+
+    for (path, contents) in files {
+        if let Err(err) = std::fs::write(&path, contents) {
+            log::error!("Couldn't write {}: {}", path, err);
+        }
+    }
+
+    if let Some(path) = arg("--export-png") {
+        let size = arg("--png-size").and_then(|size| {
+            let (width, height) = size.split_once('x')?;
+            Some((width.parse().ok()?, height.parse().ok()?))
+        });
+        let (width, height) =
+            size.unwrap_or((view.state.size.width * 4, view.state.size.height * 4));
+
+        let written = std::fs::File::create(&path).and_then(|file| {
+            let writer = std::io::BufWriter::new(file);
+            view.state
+                .export_png(export::Region::Content, width, height, writer)?
+                .into_inner()
+                .map_err(std::io::IntoInnerError::into_error)
+        });
+        if let Err(err) = written {
+            log::error!("Couldn't write {}: {}", path, err);
+        }
+    }
+}
+
+/// Value following `name` on the command line
+#[cfg(not(target_arch = "wasm32"))]
+fn arg(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+fn example_bracket() -> Bracket {
+    let mut bracket = Bracket::single_elimination(
+        [
+            "Aurora",
+            "Blizzard",
+            "Comet",
+            "Dynamo",
+            "Eclipse",
+            "Falcon",
+            "Glacier",
+            "Hurricane",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect(),
+    );
+    for (index, scores) in [[3, 1], [0, 2], [2, 1], [1, 3]].into_iter().enumerate() {
+        bracket.set_result(MatchId { round: 0, index }, scores);
+    }
+    bracket.set_result(MatchId { round: 1, index: 0 }, [2, 3]);
+    if let Some(game) = bracket.get_mut(MatchId { round: 1, index: 0 }) {
+        game.games = vec![[16, 12], [9, 16], [16, 14], [11, 16], [13, 16]];
+        game.stream = Some("twitch.tv/sandbox".to_string());
+        game.scheduled = Some("SAT 18:00 UTC".to_string());
+    }
+    if let Some(game) = bracket.get_mut(MatchId { round: 1, index: 1 }) {
+        game.stream = Some("twitch.tv/sandbox".to_string());
+        game.scheduled = Some("SAT 20:00 UTC".to_string());
+    }
+
+    bracket
+}
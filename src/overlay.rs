@@ -0,0 +1,109 @@
+use std::time::Duration;
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue, RenderPass, SurfaceConfiguration};
+
+use crate::camera::Limits;
+use crate::color::Color;
+use crate::rect::{Rect, RectPipeline};
+use crate::text::Text;
+
+/// Distance of the panel to the window edge and of its content to the panel edge
+const MARGIN: f32 = 8.0;
+const TEXT_SCALE: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+const BAR_WIDTH: f32 = 2.0;
+/// Frame time that fills the whole graph height
+const GRAPH_MAX: Duration = Duration::from_millis(50);
+/// Bars above this are drawn as slow frames, one frame at 60 Hz
+const TARGET_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+/// Heads up display with frame and scene statistics, drawn in screen space over everything else
+pub struct DebugOverlay {
+    pub visible: bool,
+    pipeline: RectPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl DebugOverlay {
+    pub fn new(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        config: &SurfaceConfiguration,
+    ) -> Self {
+        Self {
+            visible: false,
+            // Drawn straight onto the surface, so it never goes through the MSAA framebuffer
+            pipeline: RectPipeline::new(device, bind_group_layouts, config, 1),
+            format: config.format,
+        }
+    }
+
+    /// Rebuilds the panel from `lines` of text and a graph of `frame_times`, oldest first
+    pub fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        lines: &[String],
+        frame_times: impl Iterator<Item = Duration>,
+    ) {
+        let text = Text::new(
+            lines.join("\n"),
+            [MARGIN * 2.0, MARGIN * 2.0],
+            TEXT_SCALE,
+            Color::WHITE.for_format(self.format),
+        );
+        let text_size = text.size();
+
+        let graph_top = text.position[1] + text_size[1] + MARGIN;
+        let graph_bottom = graph_top + GRAPH_HEIGHT;
+
+        let mut bars = Vec::new();
+        for (index, frame_time) in frame_times.enumerate() {
+            let amount = (frame_time.as_secs_f32() / GRAPH_MAX.as_secs_f32()).min(1.0);
+            let height = (amount * GRAPH_HEIGHT).max(1.0);
+            let color = if frame_time > TARGET_FRAME_TIME {
+                Color::named("red").unwrap()
+            } else {
+                Color::named("lime").unwrap()
+            };
+
+            bars.push(Rect {
+                position: [
+                    MARGIN * 2.0 + (index as f32 + 0.5) * BAR_WIDTH,
+                    graph_bottom - height / 2.0,
+                ],
+                size: [BAR_WIDTH / 2.0, height / 2.0],
+                color: color.for_format(self.format),
+                ..Default::default()
+            });
+        }
+
+        let graph_width = bars.len() as f32 * BAR_WIDTH;
+        let area = Limits {
+            left: MARGIN,
+            right: MARGIN * 3.0 + text_size[0].max(graph_width),
+            bottom: graph_bottom,
+            top: MARGIN,
+        };
+        let background = Rect {
+            position: area.center(),
+            size: area.half_size(),
+            color: Color::BLACK.with_alpha(0.7).for_format(self.format),
+            border_radius: [4.0; 4],
+            ..Default::default()
+        };
+
+        let mut instances = vec![background];
+        instances.extend(bars);
+        instances.extend(text.rects());
+        self.pipeline.replace_instances(device, queue, instances);
+    }
+
+    /// Needs a pass without multisampling, `bind_groups` are the screen camera and gradients
+    pub fn render<'a>(&'a self, render_pass: &mut RenderPass<'a>, bind_groups: &[&'a BindGroup]) {
+        if !self.visible {
+            return;
+        }
+
+        self.pipeline.render(render_pass, bind_groups);
+    }
+}
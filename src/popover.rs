@@ -0,0 +1,124 @@
+use cgmath::vec2;
+use winit::dpi::PhysicalSize;
+
+use crate::camera::{Camera, Limits};
+use crate::rect::Rect;
+use crate::text::Text;
+use crate::theme::Theme;
+
+const PADDING: f32 = 10.0;
+/// Space between the title and the other lines
+const TITLE_GAP: f32 = 6.0;
+/// Distance to the anchor, the arrow sits in it
+const ANCHOR_GAP: f32 = 10.0;
+/// Closest the popover gets to the viewport edges
+const VIEWPORT_MARGIN: f32 = 8.0;
+const ARROW_SIZE: f32 = 10.0;
+
+/// Card with text next to a world space box, e.g. the details of a match. It's laid out
+/// in screen space, so it keeps its size while the camera zooms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Popover {
+    /// World space box the popover points at
+    pub anchor: Limits<f32>,
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+impl Popover {
+    pub fn new(anchor: Limits<f32>, title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            anchor,
+            title: title.into(),
+            lines,
+        }
+    }
+
+    /// Screen space rects in logical pixels, above the anchor or below it when there's
+    /// no room and always inside the viewport
+    pub fn rects(
+        &self,
+        camera: &Camera,
+        viewport: PhysicalSize<u32>,
+        scale_factor: f64,
+        theme: &Theme,
+        format: wgpu::TextureFormat,
+    ) -> Vec<Rect> {
+        let palette = &theme.palette;
+        let background_color = palette.overlay.for_format(format);
+        let text_scale = theme.fonts.popover;
+
+        let mut title = Text::new(
+            self.title.clone(),
+            [0.0; 2],
+            text_scale,
+            palette.text.for_format(format),
+        );
+        let mut body = Text::new(
+            self.lines.join("\n"),
+            [0.0; 2],
+            text_scale,
+            palette.muted.for_format(format),
+        );
+        let title_size = title.size();
+        let body_size = body.size();
+
+        let body_gap = if self.lines.is_empty() {
+            0.0
+        } else {
+            TITLE_GAP
+        };
+        let size = [
+            title_size[0].max(body_size[0]) + PADDING * 2.0,
+            title_size[1] + body_gap + body_size[1] + PADDING * 2.0,
+        ];
+
+        let scale = scale_factor as f32;
+        let top_left =
+            camera.world_to_screen(vec2(self.anchor.left, self.anchor.top), viewport) / scale;
+        let bottom_right =
+            camera.world_to_screen(vec2(self.anchor.right, self.anchor.bottom), viewport) / scale;
+        let center_x = (top_left.x + bottom_right.x) / 2.0;
+
+        let logical = viewport.to_logical::<f32>(scale_factor);
+        let max_x = (logical.width - size[0] - VIEWPORT_MARGIN).max(VIEWPORT_MARGIN);
+        let max_y = (logical.height - size[1] - VIEWPORT_MARGIN).max(VIEWPORT_MARGIN);
+
+        let x = (center_x - size[0] / 2.0).clamp(VIEWPORT_MARGIN, max_x);
+        let above = top_left.y - ANCHOR_GAP - size[1] >= VIEWPORT_MARGIN;
+        let y = if above {
+            top_left.y - ANCHOR_GAP - size[1]
+        } else {
+            bottom_right.y + ANCHOR_GAP
+        }
+        .clamp(VIEWPORT_MARGIN, max_y);
+
+        title.position = [x + PADDING, y + PADDING];
+        body.position = [x + PADDING, y + PADDING + title_size[1] + body_gap];
+
+        // A square turned by 45 degrees, the background covers its inner half
+        let arrow_x = center_x.clamp(x + PADDING, x + size[0] - PADDING);
+        let arrow_y = if above { y + size[1] } else { y };
+        let arrow = Rect {
+            position: [arrow_x, arrow_y],
+            size: [ARROW_SIZE / 2.0; 2],
+            rotation: std::f32::consts::FRAC_PI_4,
+            color: background_color,
+            ..Default::default()
+        };
+
+        let background = Rect {
+            position: [x + size[0] / 2.0, y + size[1] / 2.0],
+            size: [size[0] / 2.0, size[1] / 2.0],
+            color: background_color,
+            border_radius: [theme.card.corner_radius; 4],
+            ..Default::default()
+        }
+        .with_shadow([0.0, 4.0], 12.0, 0.0, palette.shadow.for_format(format));
+
+        let mut rects = vec![arrow, background];
+        rects.extend(title.rects());
+        rects.extend(body.rects());
+        rects
+    }
+}
@@ -1,16 +1,25 @@
+use cgmath::Vector2;
+use std::ops::Range;
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, RenderPass, RenderPipeline,
-    SurfaceConfiguration,
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, PipelineLayout, Queue, RenderPass,
+    RenderPipeline, ShaderModule, SurfaceConfiguration,
 };
+#[allow(clippy::module_inception)]
 mod rect;
 pub use rect::Rect;
 
+use crate::camera::Limits;
+
 pub struct RectPipeline {
     render_pipeline: RenderPipeline,
+    /// Draws the shadows of all instances, before the shapes themselves
+    shadow_pipeline: RenderPipeline,
     instances: Vec<Rect>,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     instance_buffer: Buffer,
+    /// Instances that fit into `instance_buffer`
+    capacity: usize,
 }
 
 #[repr(C)]
@@ -57,35 +66,114 @@ impl Vertex {
 impl<'a> RectPipeline {
     pub fn new(
         device: &Device,
-        bind_group_layout: &BindGroupLayout,
+        bind_group_layouts: &[&BindGroupLayout],
         config: &SurfaceConfiguration,
+        sample_count: u32,
     ) -> Self {
+        let (render_pipeline, shadow_pipeline) =
+            Self::create_render_pipelines(device, bind_group_layouts, config, sample_count);
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rect Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rect Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances: Vec<Rect> = Vec::new();
+
+        let instance_data = instances.as_slice();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rect Instance Buffer"),
+            contents: bytemuck::cast_slice(instance_data),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Self {
+            render_pipeline,
+            shadow_pipeline,
+            instances,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            capacity: 0,
+        }
+    }
+
+    /// Creates the shape and the shadow pipeline, they share the shader and vertex layout
+    fn create_render_pipelines(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) -> (RenderPipeline, RenderPipeline) {
         let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some("Rect Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("rect.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("../gradient/gradient.wgsl"),
+                    include_str!("rect.wgsl")
+                )
+                .into(),
+            ),
         });
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&bind_group_layout],
+                bind_group_layouts,
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Rect Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+        let create = |label, vs_entry, fs_entry| {
+            Self::create_render_pipeline(
+                device,
+                &shader,
+                &render_pipeline_layout,
+                config,
+                sample_count,
+                label,
+                vs_entry,
+                fs_entry,
+            )
+        };
+
+        (
+            create("Rect Render Pipeline", "vs_main", "fs_main"),
+            create("Rect Shadow Pipeline", "vs_shadow", "fs_shadow"),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_render_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        layout: &PipelineLayout,
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+        label: &str,
+        vs_entry: &str,
+        fs_entry: &str,
+    ) -> RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
+                module: shader,
+                entry_point: vs_entry,
                 buffers: &[Vertex::desc(), Rect::layout(&Rect::attributes())],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
+                module: shader,
+                entry_point: fs_entry,
                 targets: &[wgpu::ColorTargetState {
                     format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 }],
             }),
@@ -100,63 +188,130 @@ impl<'a> RectPipeline {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
+        })
+    }
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Rect Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+    /// Rebuilds the render pipelines, needed when the MSAA sample count changes
+    pub fn set_sample_count(
+        &mut self,
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        (self.render_pipeline, self.shadow_pipeline) =
+            Self::create_render_pipelines(device, bind_group_layouts, config, sample_count);
+    }
 
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Rect Index Buffer"),
-            contents: bytemuck::cast_slice(INDICES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
+    pub fn instances(&self) -> &[Rect] {
+        &self.instances
+    }
 
-        let instances = (0..10)
-            .map(move |x| {
-                let mut rect = Rect::default();
-                rect.position = [220.0 * x as f32, 200.0];
-                rect.color = [0.0, 1.0, 0.0, 1.0];
-                rect.border = 5.0;
-                rect.border_radius = [20.0; 4];
-                return rect;
-            })
-            .collect::<Vec<_>>();
+    /// Changes only reach the GPU with `write_instances`
+    pub fn instances_mut(&mut self) -> &mut [Rect] {
+        &mut self.instances
+    }
 
-        let instance_data = instances.as_slice();
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    /// Uploads the instances into the existing buffer, after changing them in place
+    pub fn write_instances(&self, queue: &Queue) {
+        if !self.instances.is_empty() {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+    }
+
+    /// Size of the instance buffer in bytes
+    pub fn instance_buffer_size(&self) -> usize {
+        self.capacity * std::mem::size_of::<Rect>()
+    }
+
+    /// Replaces all instances and uploads them into a new instance buffer
+    pub fn set_instances(&mut self, device: &Device, instances: Vec<Rect>) {
+        self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Rect Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
+        self.capacity = instances.len();
+        self.instances = instances;
+    }
 
-        Self {
-            render_pipeline,
-            instances,
-            vertex_buffer,
-            index_buffer,
-            instance_buffer,
+    /// Replaces all instances like `set_instances`, but writes them into the existing buffer
+    /// while they fit, for instances that are rebuilt every frame
+    pub fn replace_instances(&mut self, device: &Device, queue: &Queue, instances: Vec<Rect>) {
+        if instances.len() > self.capacity {
+            // Grows in steps, so a slowly growing amount doesn't need a new buffer every time
+            self.capacity = instances.len().next_power_of_two();
+            self.instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Rect Instance Buffer"),
+                size: (self.capacity * std::mem::size_of::<Rect>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
         }
+        self.instances = instances;
+        self.write_instances(queue);
     }
 
-    pub fn render(&'a self, render_pass: &mut RenderPass<'a>, camera_bind_group: &'a BindGroup) {
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &camera_bind_group, &[]);
+    /// `bind_groups` are the camera and gradient bind groups, in group order
+    pub fn render(&'a self, render_pass: &mut RenderPass<'a>, bind_groups: &[&'a BindGroup]) {
+        let instance_amount = self.instances.len() as u32;
+        self.render_range(render_pass, bind_groups, 0..instance_amount);
+    }
+
+    /// Renders only the instances in `instances`, e.g. the ones belonging to a group
+    pub fn render_range(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        bind_groups: &[&'a BindGroup],
+        instances: Range<u32>,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
 
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
         let index_amount = INDICES.len() as u32;
-        let instance_amount = self.instances.len() as u32;
 
-        render_pass.draw_indexed(0..index_amount, 0, 0..instance_amount);
+        // All shadows go below all shapes of the range, which is cheaper than a pipeline switch
+        // per instance. Instances without a shadow collapse to nothing in the vertex shader.
+        render_pass.set_pipeline(&self.shadow_pipeline);
+        render_pass.draw_indexed(0..index_amount, 0, instances.clone());
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw_indexed(0..index_amount, 0, instances);
+    }
+
+    /// Index of the topmost rect containing `point`, rects drawn later are on top
+    pub fn hit_test(&self, point: Vector2<f32>) -> Option<usize> {
+        self.instances.iter().rposition(|rect| rect.contains(point))
+    }
+
+    /// Bounding box around all rects, `None` if there are none
+    pub fn bounds(&self) -> Option<Limits<f32>> {
+        self.instances
+            .iter()
+            .map(Rect::bounds)
+            .reduce(|a, b| Limits {
+                left: a.left.min(b.left),
+                right: a.right.max(b.right),
+                bottom: a.bottom.max(b.bottom),
+                top: a.top.min(b.top),
+            })
     }
 }
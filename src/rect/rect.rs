@@ -1,15 +1,37 @@
+use cgmath::{vec2, InnerSpace, Vector2};
 use wgpu::vertex_attr_array;
 
+use crate::camera::Limits;
+use crate::gradient::GradientId;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Rect {
+    /// Center of the unrotated rect in world space
     pub position: [f32; 2],
+    /// Rotation in radians around `pivot`
     pub rotation: f32,
+    /// Straight alpha color in surface space, see `Color::for_format`
     pub color: [f32; 4],
+    /// Half the width and height, the rect reaches this far from `position` in each direction
     pub size: [f32; 2],
+    /// Corner radii in the order top left, top right, bottom right, bottom left
     pub border_radius: [f32; 4],
     pub border: f32,
     pub border_color: [f32; 4],
+    /// Rotation origin relative to the rect, (0, 0) is the top left and (1, 1) the bottom right corner
+    pub pivot: [f32; 2],
+    /// Gradient used instead of `color`, 0 for none
+    pub fill_gradient: GradientId,
+    /// Gradient used instead of `border_color`, 0 for none
+    pub border_gradient: GradientId,
+    /// Shadow offset in world units, applied after rotation
+    pub shadow_offset: [f32; 2],
+    pub shadow_blur: f32,
+    /// Grows (or shrinks when negative) the shadow before blurring
+    pub shadow_spread: f32,
+    /// Fully transparent disables the shadow. A glow is a shadow without offset.
+    pub shadow_color: [f32; 4],
 }
 
 impl Default for Rect {
@@ -22,20 +44,134 @@ impl Default for Rect {
             border_radius: [0.0; 4],
             border: 0.0,
             border_color: [1.0; 4],
+            pivot: [0.5; 2],
+            fill_gradient: 0,
+            border_gradient: 0,
+            shadow_offset: [0.0; 2],
+            shadow_blur: 0.0,
+            shadow_spread: 0.0,
+            shadow_color: [0.0; 4],
         }
     }
 }
 
 impl Rect {
-    pub fn attributes() -> [wgpu::VertexAttribute; 7] {
-        vertex_attr_array!(1 => Float32x2, 2 => Float32, 3 => Float32x4, 4 => Float32x2, 5 => Float32x4, 6 => Float32, 7 => Float32x4)
+    pub fn attributes() -> [wgpu::VertexAttribute; 12] {
+        vertex_attr_array!(1 => Float32x2, 2 => Float32, 3 => Float32x4, 4 => Float32x2, 5 => Float32x4, 6 => Float32, 7 => Float32x4, 8 => Float32x2, 9 => Uint32, 10 => Uint32, 11 => Float32x4, 12 => Float32x4)
+    }
+
+    /// Shadow around the shape with the same outline. The shadows of all rects are drawn
+    /// before the rects themselves, so a shadow never falls on another rect, not even one
+    /// drawn earlier.
+    pub fn with_shadow(self, offset: [f32; 2], blur: f32, spread: f32, color: [f32; 4]) -> Self {
+        Self {
+            shadow_offset: offset,
+            shadow_blur: blur,
+            shadow_spread: spread,
+            shadow_color: color,
+            ..self
+        }
     }
 
-    pub fn layout(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout {
+    /// Centered shadow, usually in a bright color
+    pub fn with_glow(self, blur: f32, color: [f32; 4]) -> Self {
+        self.with_shadow([0.0; 2], blur, 0.0, color)
+    }
+
+    pub fn layout(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout<'_> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Rect>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Instance,
             attributes,
         }
     }
+
+    /// Pivot relative to the center, in the local frame
+    fn pivot_local(&self) -> Vector2<f32> {
+        vec2(
+            (self.pivot[0] * 2.0 - 1.0) * self.size[0],
+            (self.pivot[1] * 2.0 - 1.0) * self.size[1],
+        )
+    }
+
+    /// Maps a point in the rects local frame (relative to its center) to world space
+    pub fn local_to_world(&self, local: Vector2<f32>) -> Vector2<f32> {
+        let pivot = self.pivot_local();
+        let (sin, cos) = self.rotation.sin_cos();
+
+        let rel = local - pivot;
+        let rotated = vec2(cos * rel.x - sin * rel.y, sin * rel.x + cos * rel.y);
+
+        vec2(self.position[0], self.position[1]) + pivot + rotated
+    }
+
+    /// Maps a world space point into the rects local frame (relative to its center)
+    pub fn world_to_local(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let pivot = self.pivot_local();
+        let (sin, cos) = self.rotation.sin_cos();
+
+        let rel = point - vec2(self.position[0], self.position[1]) - pivot;
+        let unrotated = vec2(cos * rel.x + sin * rel.y, -sin * rel.x + cos * rel.y);
+
+        unrotated + pivot
+    }
+
+    /// The four corners in world space, clockwise starting at the top left
+    pub fn corners(&self) -> [Vector2<f32>; 4] {
+        let half = vec2(self.size[0], self.size[1]);
+
+        [
+            self.local_to_world(vec2(-half.x, -half.y)),
+            self.local_to_world(vec2(half.x, -half.y)),
+            self.local_to_world(vec2(half.x, half.y)),
+            self.local_to_world(vec2(-half.x, half.y)),
+        ]
+    }
+
+    /// Axis aligned bounding box of the rotated rect
+    pub fn bounds(&self) -> Limits<f32> {
+        let corners = self.corners();
+        let mut bounds = Limits {
+            left: f32::MAX,
+            right: f32::MIN,
+            bottom: f32::MIN,
+            top: f32::MAX,
+        };
+
+        for corner in corners {
+            bounds.left = bounds.left.min(corner.x);
+            bounds.right = bounds.right.max(corner.x);
+            bounds.top = bounds.top.min(corner.y);
+            bounds.bottom = bounds.bottom.max(corner.y);
+        }
+
+        bounds
+    }
+
+    /// Signed distance from a point in the local frame to the rounded outline, same as in `rect.wgsl`
+    pub fn signed_distance(&self, local: Vector2<f32>) -> f32 {
+        let half = vec2(self.size[0], self.size[1]);
+        let radius = corner_radius(self.border_radius, local).min(half.x.min(half.y));
+
+        let q = vec2(
+            local.x.abs() - half.x + radius,
+            local.y.abs() - half.y + radius,
+        );
+        let outside = vec2(q.x.max(0.0), q.y.max(0.0));
+
+        q.x.max(q.y).min(0.0) + outside.magnitude() - radius
+    }
+
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        self.signed_distance(self.world_to_local(point)) <= 0.0
+    }
+}
+
+fn corner_radius(radii: [f32; 4], local: Vector2<f32>) -> f32 {
+    match (local.x < 0.0, local.y < 0.0) {
+        (true, true) => radii[0],
+        (false, true) => radii[1],
+        (false, false) => radii[2],
+        (true, false) => radii[3],
+    }
 }
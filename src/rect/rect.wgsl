@@ -6,10 +6,17 @@ struct InstanceInput {
     [[location(5)]] border_radius: vec4<f32>;
     [[location(6)]] border_width: f32;
     [[location(7)]] border_color: vec4<f32>;
+    [[location(8)]] pivot: vec2<f32>;
+    [[location(9)]] fill_gradient: u32;
+    [[location(10)]] border_gradient: u32;
+    // Offset, blur and spread
+    [[location(11)]] shadow: vec4<f32>;
+    [[location(12)]] shadow_color: vec4<f32>;
 };
 
 struct CameraUniform {
     view_proj: mat4x4<f32>;
+    pixel_size: f32;
 };
 
 [[group(0), binding(0)]]
@@ -17,98 +24,172 @@ var<uniform> camera: CameraUniform;
 
 struct VertexInput {
     [[location(0)]] v_position: vec2<f32>;
-    [[builtin(vertex_index)]] index: u32;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    // Full size, what gradients are spread over
     [[location(1)]] size: vec2<f32>;
-    [[location(2)]] position: vec2<f32>;
+    // Position relative to the rect center, before rotation
+    [[location(2)]] local: vec2<f32>;
     [[location(3)]] border_width: f32;
     [[location(4)]] border_color: vec4<f32>;
     [[location(5)]] border_radius: vec4<f32>;
-    [[location(6)]] index: u32;
+    [[location(6), interpolate(flat)]] fill_gradient: u32;
+    [[location(7), interpolate(flat)]] border_gradient: u32;
 };
 
+// Maps a position relative to the rect center into world space
+fn local_to_world(local: vec2<f32>, instance: InstanceInput) -> vec2<f32> {
+    // Rotate around the pivot, which is given from the top left (0, 0) to the bottom right
+    // corner (1, 1). Instance sizes are half extents.
+    let pivot = (instance.pivot * 2.0 - 1.0) * instance.size;
+    let relative = local - pivot;
+    let s = sin(instance.rotation);
+    let c = cos(instance.rotation);
+    let rotated = vec2<f32>(
+        c * relative.x - s * relative.y,
+        s * relative.x + c * relative.y
+    );
+    return instance.position + pivot + rotated;
+}
+
 [[stage(vertex)]]
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput
 ) -> VertexOutput {
-    let transform: mat4x4<f32> = mat4x4<f32>(
-        vec4<f32>(instance.size.x, 0.0, 0.0, 0.0),
-        vec4<f32>(0.0, instance.size.y, 0.0, 0.0),
-        vec4<f32>(0.0, 0.0, 1.0, 0.0),
-        vec4<f32>(instance.position, 0.0, 1.0),
-    );
+    // Pad the quad by a pixel so the anti-aliased edge isn't clipped
+    let local = model.v_position * (instance.size + camera.pixel_size);
+    let world = local_to_world(local, instance);
 
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * transform * vec4<f32>(model.v_position, 0.0, 1.0);
+    out.clip_position = camera.view_proj * vec4<f32>(world, 0.0, 1.0);
     out.color = instance.color;
-    out.size = instance.size;
-    out.position = model.v_position;
+    out.size = instance.size * 2.0;
+    out.local = local;
     out.border_radius = instance.border_radius;
     out.border_width = instance.border_width;
     out.border_color = instance.border_color;
-    out.index = model.index;
+    out.fill_gradient = instance.fill_gradient;
+    out.border_gradient = instance.border_gradient;
     return out;
 }
 
-fn distance_alg(
-    frag_coord: vec2<f32>,
-    position: vec2<f32>,
-    size: vec2<f32>,
+// Radii are ordered top left, top right, bottom right, bottom left
+fn corner_radius(local: vec2<f32>, radii: vec4<f32>) -> f32 {
+    if (local.x < 0.0) {
+        if (local.y < 0.0) {
+            return radii.x;
+        }
+        return radii.w;
+    }
+    if (local.y < 0.0) {
+        return radii.y;
+    }
+    return radii.z;
+}
+
+fn rounded_rect_sdf(
+    local: vec2<f32>,
+    half_size: vec2<f32>,
     radius: f32
 ) -> f32 {
-    var inner_size: vec2<f32> = size - vec2<f32>(radius, radius) * 2.0;
-    var top_left: vec2<f32> = position + vec2<f32>(radius, radius);
-    var bottom_right: vec2<f32> = top_left + inner_size - 2.0;
-
-    var top_left_distance: vec2<f32> = top_left - frag_coord;
-    var bottom_right_distance: vec2<f32> = frag_coord - bottom_right;
-
-    var dist: vec2<f32> = vec2<f32>(
-        max(max(top_left_distance.x, bottom_right_distance.x), 0.0),
-        max(max(top_left_distance.y, bottom_right_distance.y), 0.0)
-    );
-
-    return sqrt(dist.x * dist.x + dist.y * dist.y);
+    let q = abs(local) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0, 0.0))) - radius;
 }
 
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    var color = in.color;
-    let aspect = in.size.x / in.size.y; 
-    
-    let norm_pos = (in.position + 1.0) / 2.0;
-    let border_radius = in.border_radius[in.index];
-    let coords = norm_pos * in.size;
-
-    var dist: f32 = distance_alg(
-        coords,
-        norm_pos,
-        in.size,
-        border_radius,
+    let half_size = in.size / 2.0;
+    let radius = min(
+        corner_radius(in.local, in.border_radius),
+        min(half_size.x, half_size.y)
     );
 
+    let dist = rounded_rect_sdf(in.local, half_size, radius);
+    // Half a screen pixel in local units, keeps edges ~1px wide at any zoom
+    let aa = fwidth(dist) * 0.5;
+
+    var color = in.color;
+    if (in.fill_gradient > 0u) {
+        color = gradient_color(in.fill_gradient, in.local, in.size);
+    }
+
     if (in.border_width > 0.0) {
-      var internal_border: f32 = max(
-          in.border_radius.x - in.border_width,
-          0.0
-      );
-
-      if (dist > internal_border) {
-          color = in.border_color;
-      }
+        var border_color = in.border_color;
+        if (in.border_gradient > 0u) {
+            border_color = gradient_color(in.border_gradient, in.local, in.size);
+        }
+
+        let border_mix = smoothStep(
+            -in.border_width - aa,
+            -in.border_width + aa,
+            dist
+        );
+        color = mix(color, border_color, border_mix);
     }
 
-    var radius_alpha: f32 = 1.0 - smoothStep(
-        max(border_radius - 0.2, 0.0),
-        border_radius + 0.2,
-        dist
+    let alpha = 1.0 - smoothStep(-aa, aa, dist);
+
+    // Output premultiplied alpha to match the pipelines blend state
+    let a = color.w * alpha;
+    return vec4<f32>(color.xyz * a, a);
+}
+
+struct ShadowOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    // Position relative to the rect center, before rotation and offset
+    [[location(1)]] local: vec2<f32>;
+    // Half size and radii already include the spread
+    [[location(2)]] half_size: vec2<f32>;
+    [[location(3)]] border_radius: vec4<f32>;
+    [[location(4)]] blur: f32;
+};
+
+[[stage(vertex)]]
+fn vs_shadow(
+    model: VertexInput,
+    instance: InstanceInput
+) -> ShadowOutput {
+    var out: ShadowOutput;
+
+    // Collapse the quad when there is no shadow, so it costs no fragments
+    if (instance.shadow_color.w <= 0.0) {
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        return out;
+    }
+
+    let offset = instance.shadow.xy;
+    let blur = instance.shadow.z;
+    let spread = instance.shadow.w;
+
+    let half_size = max(instance.size + spread, vec2<f32>(0.0, 0.0));
+    let local = model.v_position * (half_size + blur + camera.pixel_size);
+    let world = local_to_world(local, instance) + offset;
+
+    out.clip_position = camera.view_proj * vec4<f32>(world, 0.0, 1.0);
+    out.color = instance.shadow_color;
+    out.local = local;
+    out.half_size = half_size;
+    out.border_radius = max(instance.border_radius + spread, vec4<f32>(0.0));
+    out.blur = blur;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_shadow(in: ShadowOutput) -> [[location(0)]] vec4<f32> {
+    let radius = min(
+        corner_radius(in.local, in.border_radius),
+        min(in.half_size.x, in.half_size.y)
     );
 
-    return vec4<f32>(color.xyz, color.w * radius_alpha);
+    let dist = rounded_rect_sdf(in.local, in.half_size, radius);
+    // Blur the edge over the blur radius, but never sharper than a pixel
+    let softness = max(in.blur, fwidth(dist) * 0.5);
+    let alpha = in.color.w * (1.0 - smoothStep(-softness, softness, dist));
+
+    return vec4<f32>(in.color.xyz * alpha, alpha);
 }
- 
@@ -0,0 +1,333 @@
+//! Compact encoding of the JSON value tree. Object keys are written once and referenced by
+//! index afterwards, numbers take as few bytes as they need.
+
+use serde_json::{Map, Number, Value};
+
+use super::SceneError;
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+/// Followed by a varint
+const UINT: u8 = 3;
+/// Followed by the varint of `-1 - n`
+const NEGATIVE_INT: u8 = 4;
+const F32: u8 = 5;
+const F64: u8 = 6;
+/// Followed by the byte length and UTF-8
+const STRING: u8 = 7;
+/// Followed by the element count and the elements
+const ARRAY: u8 = 8;
+/// Followed by the entry count, every entry is a key reference and a value
+const OBJECT: u8 = 9;
+
+/// Arrays and objects nested deeper than this are rejected before they overflow the stack,
+/// the same limit as serde_json
+const MAX_DEPTH: usize = 128;
+
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut encoder = Encoder {
+        bytes: Vec::new(),
+        keys: Vec::new(),
+    };
+    encoder.value(value);
+
+    encoder.bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Value, SceneError> {
+    let mut decoder = Decoder {
+        bytes,
+        keys: Vec::new(),
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if !decoder.bytes.is_empty() {
+        return Err(SceneError::Corrupt("trailing bytes"));
+    }
+
+    Ok(value)
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+    /// Keys written so far, in order
+    keys: Vec<String>,
+}
+
+impl Encoder {
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.bytes.push(NULL),
+            Value::Bool(false) => self.bytes.push(FALSE),
+            Value::Bool(true) => self.bytes.push(TRUE),
+            Value::Number(number) => self.number(number),
+            Value::String(string) => {
+                self.bytes.push(STRING);
+                self.string(string);
+            }
+            Value::Array(values) => {
+                self.bytes.push(ARRAY);
+                self.varint(values.len() as u64);
+                for value in values {
+                    self.value(value);
+                }
+            }
+            Value::Object(map) => {
+                self.bytes.push(OBJECT);
+                self.varint(map.len() as u64);
+                for (key, value) in map {
+                    self.key(key);
+                    self.value(value);
+                }
+            }
+        }
+    }
+
+    fn number(&mut self, number: &Number) {
+        if let Some(n) = number.as_u64() {
+            self.bytes.push(UINT);
+            self.varint(n);
+        } else if let Some(n) = number.as_i64() {
+            self.bytes.push(NEGATIVE_INT);
+            self.varint((-1 - n) as u64);
+        } else {
+            let n = number.as_f64().unwrap_or_default();
+            // Scenes are made of f32, those survive the trip through f64 unchanged
+            if n as f32 as f64 == n {
+                self.bytes.push(F32);
+                self.bytes.extend_from_slice(&(n as f32).to_le_bytes());
+            } else {
+                self.bytes.push(F64);
+                self.bytes.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+    }
+
+    /// Index of a known key, or the index the next key gets followed by the key itself
+    fn key(&mut self, key: &str) {
+        match self.keys.iter().position(|known| known == key) {
+            Some(index) => self.varint(index as u64),
+            None => {
+                self.varint(self.keys.len() as u64);
+                self.string(key);
+                self.keys.push(key.to_string());
+            }
+        }
+    }
+
+    fn string(&mut self, string: &str) {
+        self.varint(string.len() as u64);
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    /// LEB128, 7 bits per byte and the high bit set on all but the last
+    fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+}
+
+struct Decoder<'a> {
+    /// What is left to read
+    bytes: &'a [u8],
+    keys: Vec<String>,
+    /// Arrays and objects around the value being read
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn value(&mut self) -> Result<Value, SceneError> {
+        let value = match self.byte()? {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            UINT => Value::from(self.varint()?),
+            NEGATIVE_INT => {
+                let n = i64::try_from(self.varint()?)
+                    .map_err(|_| SceneError::Corrupt("integer out of range"))?;
+                Value::from(-1 - n)
+            }
+            F32 => Value::from(f32::from_le_bytes(self.array()?) as f64),
+            F64 => Value::from(f64::from_le_bytes(self.array()?)),
+            STRING => Value::String(self.string()?),
+            ARRAY => {
+                let len = self.len()?;
+                self.enter()?;
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(self.value()?);
+                }
+                self.depth -= 1;
+                Value::Array(values)
+            }
+            OBJECT => {
+                let len = self.len()?;
+                self.enter()?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let key = self.key()?;
+                    map.insert(key, self.value()?);
+                }
+                self.depth -= 1;
+                Value::Object(map)
+            }
+            _ => return Err(SceneError::Corrupt("unknown value tag")),
+        };
+
+        Ok(value)
+    }
+
+    /// Goes one array or object deeper, `value` leaves it again
+    fn enter(&mut self) -> Result<(), SceneError> {
+        if self.depth == MAX_DEPTH {
+            return Err(SceneError::Corrupt("nested too deep"));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn key(&mut self) -> Result<String, SceneError> {
+        let index = self.varint()?;
+        if let Some(key) = usize::try_from(index).ok().and_then(|i| self.keys.get(i)) {
+            return Ok(key.clone());
+        }
+        if index != self.keys.len() as u64 {
+            return Err(SceneError::Corrupt("unknown key"));
+        }
+
+        let key = self.string()?;
+        self.keys.push(key.clone());
+        Ok(key)
+    }
+
+    fn string(&mut self) -> Result<String, SceneError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SceneError::Corrupt("invalid UTF-8"))
+    }
+
+    /// A count, which can't be larger than the bytes that are left
+    fn len(&mut self) -> Result<usize, SceneError> {
+        let len = self.varint()?;
+        if len > self.bytes.len() as u64 {
+            return Err(SceneError::Corrupt("length past the end"));
+        }
+
+        Ok(len as usize)
+    }
+
+    fn varint(&mut self) -> Result<u64, SceneError> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+
+        Err(SceneError::Corrupt("varint too long"))
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SceneError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn byte(&mut self) -> Result<u8, SceneError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SceneError> {
+        if len > self.bytes.len() {
+            return Err(SceneError::Corrupt("unexpected end"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_every_kind_of_value() {
+        let value = json!({
+            "null": null,
+            "flags": [true, false],
+            "numbers": [0, 127, 128, u64::MAX, -1, i64::MIN, 0.5, 0.1],
+            "text": "Aurora ❄",
+            "nested": [{ "text": "repeated key" }, { "text": "" }],
+        });
+
+        assert_eq!(decode(&encode(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn writes_keys_once() {
+        let value = json!([{ "participants": 1 }, { "participants": 2 }]);
+        let bytes = encode(&value);
+
+        let written = bytes.windows(12).filter(|w| w == b"participants");
+        assert_eq!(written.count(), 1);
+    }
+
+    #[test]
+    fn rejects_every_truncation() {
+        let bytes = encode(&json!({ "rects": [{ "position": [1.5, -2.0] }], "name": "x" }));
+
+        for len in 0..bytes.len() {
+            assert!(
+                decode(&bytes[..len]).is_err(),
+                "{} of {} bytes",
+                len,
+                bytes.len()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_input() {
+        let corrupt: [&[u8]; 6] = [
+            &[42],
+            &[NULL, NULL],
+            &[STRING, 2, 0xff, 0xfe],
+            &[OBJECT, 1, 1, NULL],
+            &[ARRAY, 0xff, 0xff, 0xff, 0xff, 0x0f],
+            &[
+                UINT, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+            ],
+        ];
+
+        for bytes in corrupt {
+            assert!(decode(bytes).is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| {
+            let mut bytes = [ARRAY, 1].repeat(depth);
+            bytes.push(NULL);
+            bytes
+        };
+
+        assert!(decode(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            decode(&nested(MAX_DEPTH + 1)),
+            Err(SceneError::Corrupt("nested too deep"))
+        ));
+        // Deep enough to overflow the stack without the limit
+        assert!(decode(&nested(1_000_000)).is_err());
+    }
+}
@@ -0,0 +1,586 @@
+mod binary;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::bracket::Tournament;
+use crate::circle::Circle;
+use crate::color::Color;
+use crate::compositor::{Filter, Group};
+use crate::gradient::{Gradient, GradientId, MAX_GRADIENTS};
+use crate::rect::Rect;
+use crate::theme::{Roles, Theme};
+
+/// Version written into new files, older ones are migrated when loaded
+pub const VERSION: u32 = 2;
+/// Start of the binary form, JSON can't start with it
+const MAGIC: &[u8; 4] = b"BRSC";
+
+/// Migrations from every older version to the next, the first one turns version 0 into 1
+const MIGRATIONS: [fn(Value) -> Value; VERSION as usize] = [tournament_to_scene, theme_styles];
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The binary form is cut off or damaged
+    Corrupt(&'static str),
+    /// Written by a newer version of the viewer
+    UnknownVersion(u64),
+    /// Well formed, but refers to gradients or instances it doesn't have
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Json(err) => write!(f, "Invalid scene: {}", err),
+            SceneError::Corrupt(reason) => write!(f, "Corrupt scene file: {}", reason),
+            SceneError::UnknownVersion(version) => write!(
+                f,
+                "Scene version {} is newer than the supported version {}",
+                version, VERSION
+            ),
+            SceneError::Invalid(reason) => write!(f, "Invalid scene: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(err: serde_json::Error) -> Self {
+        SceneError::Json(err)
+    }
+}
+
+/// Everything drawn in a `State`, saved with `State::save_scene` and shown again with
+/// `State::load_scene`. Colors are sRGB, whatever the surface format was. Only `version` and
+/// `bracket` are required, a file without a version is read as a plain tournament:
+///
+/// ```json
+/// {
+///     "version": 2,
+///     "bracket": { "participants": ["Aurora", "Blizzard"] },
+///     "camera": { "center": [200.0, 120.0], "zoom": 1.5 },
+///     "world": { "rects": [{ "position": [0.0, 400.0], "color": [1.0, 0.8, 0.0, 1.0] }] }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    #[serde(default)]
+    pub theme: Theme,
+    pub bracket: Tournament,
+    /// `None` fits the view to the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraData>,
+    /// Table the `fill_gradient` and `border_gradient` ids refer to, starting at 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gradients: Vec<Gradient>,
+    /// Drawn after the bracket, which isn't saved as primitives
+    #[serde(default)]
+    pub world: Primitives,
+    #[serde(default)]
+    pub screen: Primitives,
+    /// Ranges count the rects of the bracket, the world rects come right after them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+    #[serde(default)]
+    pub frame_filter: Filter,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraData {
+    /// World point in the middle of the view
+    pub center: [f32; 2],
+    /// World units per pixel
+    pub zoom: f32,
+}
+
+/// Rects and circles of one `Space`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Primitives {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rects: Vec<RectData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub circles: Vec<CircleData>,
+}
+
+/// A `Rect` with sRGB colors, missing fields are the ones of `Rect::default`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RectData {
+    pub position: [f32; 2],
+    pub rotation: f32,
+    pub color: Color,
+    pub size: [f32; 2],
+    pub border_radius: [f32; 4],
+    pub border: f32,
+    pub border_color: Color,
+    pub pivot: [f32; 2],
+    pub fill_gradient: GradientId,
+    pub border_gradient: GradientId,
+    pub shadow_offset: [f32; 2],
+    pub shadow_blur: f32,
+    pub shadow_spread: f32,
+    pub shadow_color: Color,
+    /// Theme colors that replace the ones above when the theme changes
+    #[serde(skip_serializing_if = "Roles::is_empty")]
+    pub roles: Roles,
+}
+
+impl RectData {
+    /// Top left corner before the rotation, `position` is the center
+    pub fn top_left(&self) -> [f32; 2] {
+        [
+            self.position[0] - self.size[0],
+            self.position[1] - self.size[1],
+        ]
+    }
+
+    /// Width and height, `size` holds half of them like `Rect::size`
+    pub fn full_size(&self) -> [f32; 2] {
+        self.size.map(|half| half * 2.0)
+    }
+
+    pub fn from_rect(rect: &Rect, format: wgpu::TextureFormat) -> Self {
+        Self {
+            position: rect.position,
+            rotation: rect.rotation,
+            color: Color::from_format(rect.color, format),
+            size: rect.size,
+            border_radius: rect.border_radius,
+            border: rect.border,
+            border_color: Color::from_format(rect.border_color, format),
+            pivot: rect.pivot,
+            fill_gradient: rect.fill_gradient,
+            border_gradient: rect.border_gradient,
+            shadow_offset: rect.shadow_offset,
+            shadow_blur: rect.shadow_blur,
+            shadow_spread: rect.shadow_spread,
+            shadow_color: Color::from_format(rect.shadow_color, format),
+            roles: Roles::default(),
+        }
+    }
+
+    pub fn to_rect(self, format: wgpu::TextureFormat) -> Rect {
+        Rect {
+            position: self.position,
+            rotation: self.rotation,
+            color: self.color.for_format(format),
+            size: self.size,
+            border_radius: self.border_radius,
+            border: self.border,
+            border_color: self.border_color.for_format(format),
+            pivot: self.pivot,
+            fill_gradient: self.fill_gradient,
+            border_gradient: self.border_gradient,
+            shadow_offset: self.shadow_offset,
+            shadow_blur: self.shadow_blur,
+            shadow_spread: self.shadow_spread,
+            shadow_color: self.shadow_color.for_format(format),
+        }
+    }
+}
+
+impl Default for RectData {
+    fn default() -> Self {
+        // The default colors are white and transparent, the same in every format
+        Self::from_rect(&Rect::default(), wgpu::TextureFormat::Rgba8Unorm)
+    }
+}
+
+/// A `Circle` with sRGB colors, missing fields are the ones of `Circle::default`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircleData {
+    pub position: [f32; 2],
+    pub color: Color,
+    pub radius: f32,
+    pub border: f32,
+    pub border_color: Color,
+    pub fill_gradient: GradientId,
+    pub border_gradient: GradientId,
+    pub shadow_offset: [f32; 2],
+    pub shadow_blur: f32,
+    pub shadow_spread: f32,
+    pub shadow_color: Color,
+    /// Theme colors that replace the ones above when the theme changes
+    #[serde(skip_serializing_if = "Roles::is_empty")]
+    pub roles: Roles,
+}
+
+impl CircleData {
+    pub fn from_circle(circle: &Circle, format: wgpu::TextureFormat) -> Self {
+        Self {
+            position: circle.position,
+            color: Color::from_format(circle.color, format),
+            radius: circle.radius,
+            border: circle.border,
+            border_color: Color::from_format(circle.border_color, format),
+            fill_gradient: circle.fill_gradient,
+            border_gradient: circle.border_gradient,
+            shadow_offset: circle.shadow_offset,
+            shadow_blur: circle.shadow_blur,
+            shadow_spread: circle.shadow_spread,
+            shadow_color: Color::from_format(circle.shadow_color, format),
+            roles: Roles::default(),
+        }
+    }
+
+    pub fn to_circle(self, format: wgpu::TextureFormat) -> Circle {
+        Circle {
+            position: self.position,
+            color: self.color.for_format(format),
+            radius: self.radius,
+            border: self.border,
+            border_color: self.border_color.for_format(format),
+            fill_gradient: self.fill_gradient,
+            border_gradient: self.border_gradient,
+            shadow_offset: self.shadow_offset,
+            shadow_blur: self.shadow_blur,
+            shadow_spread: self.shadow_spread,
+            shadow_color: self.shadow_color.for_format(format),
+        }
+    }
+}
+
+impl Default for CircleData {
+    fn default() -> Self {
+        Self::from_circle(&Circle::default(), wgpu::TextureFormat::Rgba8Unorm)
+    }
+}
+
+impl SceneFile {
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        Self::from_value(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads the compact form written by `to_binary`
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, SceneError> {
+        let value = bytes
+            .strip_prefix(MAGIC)
+            .ok_or(SceneError::Corrupt("not a binary scene"))?;
+        Self::from_value(binary::decode(value)?)
+    }
+
+    /// Same content as the JSON, with every key written only once and numbers packed
+    pub fn to_binary(&self) -> Result<Vec<u8>, SceneError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(binary::encode(&serde_json::to_value(self)?));
+        Ok(bytes)
+    }
+
+    /// Either form, told apart by the start of the binary one
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SceneError> {
+        if bytes.starts_with(MAGIC) {
+            return Self::from_binary(bytes);
+        }
+
+        let json = std::str::from_utf8(bytes).map_err(|_| SceneError::Corrupt("not UTF-8"))?;
+        Self::from_json(json)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, SceneError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Writes JSON if the file name ends in `.json`, the binary form otherwise
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let bytes = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            self.to_json()?.into_bytes()
+        } else {
+            self.to_binary()?
+        };
+
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Checks that gradient ids and group ranges point at things the scene has, anything else
+    /// would be drawn out of bounds. Group ranges count the `bracket_rects` the bracket is
+    /// drawn with before the world rects, but may only take in world rects.
+    pub fn validate(&self, bracket_rects: usize) -> Result<(), SceneError> {
+        if self.gradients.len() > MAX_GRADIENTS {
+            return Err(SceneError::Invalid(format!(
+                "{} gradients, only {} fit",
+                self.gradients.len(),
+                MAX_GRADIENTS
+            )));
+        }
+
+        let mut ids = Vec::new();
+        for primitives in [&self.world, &self.screen] {
+            let rects = primitives.rects.iter();
+            ids.extend(rects.flat_map(|r| [r.fill_gradient, r.border_gradient]));
+            let circles = primitives.circles.iter();
+            ids.extend(circles.flat_map(|c| [c.fill_gradient, c.border_gradient]));
+        }
+        if let Some(id) = ids
+            .into_iter()
+            .find(|id| *id as usize > self.gradients.len())
+        {
+            return Err(SceneError::Invalid(format!(
+                "gradient {} doesn't exist",
+                id
+            )));
+        }
+
+        let rects = (bracket_rects + self.world.rects.len()) as u32;
+        let circles = self.world.circles.len() as u32;
+        if let Some(index) = self.groups.iter().position(|g| !g.fits(rects, circles)) {
+            return Err(SceneError::Invalid(format!(
+                "group {} covers instances the scene doesn't have",
+                index
+            )));
+        }
+        let bracket_rects = bracket_rects as u32;
+        if let Some(index) =
+            (self.groups.iter()).position(|g| !g.rects.is_empty() && g.rects.start < bracket_rects)
+        {
+            return Err(SceneError::Invalid(format!(
+                "group {} covers rects of the bracket",
+                index
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn from_value(value: Value) -> Result<Self, SceneError> {
+        Ok(serde_json::from_value(migrate(value)?)?)
+    }
+}
+
+/// Brings a scene of any known version to `VERSION`, files without a version are version 0
+fn migrate(mut scene: Value) -> Result<Value, SceneError> {
+    let version = scene.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > VERSION as u64 {
+        return Err(SceneError::UnknownVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        scene = migration(scene);
+    }
+
+    Ok(scene)
+}
+
+/// Version 0 is a plain tournament as `Tournament::to_json` writes it
+fn tournament_to_scene(tournament: Value) -> Value {
+    json!({ "version": 1, "bracket": tournament })
+}
+
+/// Version 1 only had a background color, version 2 keeps it in a whole `Theme`
+fn theme_styles(mut scene: Value) -> Value {
+    if let Some(background) = scene
+        .as_object_mut()
+        .and_then(|scene| scene.remove("background"))
+    {
+        // The rest of the theme keeps the defaults
+        scene["theme"] = json!({ "background": background });
+    }
+
+    scene["version"] = json!(2);
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bracket::MatchData;
+    use crate::theme::Role;
+
+    fn populated() -> SceneFile {
+        SceneFile {
+            version: VERSION,
+            theme: Theme::light(),
+            bracket: Tournament {
+                participants: vec!["Aurora".to_string(), "Blizzard".to_string()],
+                matches: vec![MatchData {
+                    round: 0,
+                    index: 0,
+                    scores: Some([3, 1]),
+                    games: vec![[16, 12], [9, 16]],
+                    stream: Some("twitch.tv/sandbox".to_string()),
+                    scheduled: None,
+                }],
+            },
+            camera: Some(CameraData {
+                center: [200.0, 120.5],
+                zoom: 1.5,
+            }),
+            gradients: vec![Gradient::linear(
+                0.25,
+                &[(0.0, Color::rgb(255, 0, 0)), (1.0, Color::WHITE)],
+            )],
+            world: Primitives {
+                rects: vec![RectData {
+                    position: [-10.0, 400.0],
+                    rotation: 0.3,
+                    fill_gradient: 1,
+                    shadow_blur: 6.0,
+                    roles: Roles {
+                        border: Some(Role::CardBorder),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }],
+                circles: vec![CircleData {
+                    radius: 12.0,
+                    color: Color::rgba(10, 20, 30, 0.5),
+                    ..Default::default()
+                }],
+            },
+            screen: Primitives {
+                rects: vec![RectData::default()],
+                circles: Vec::new(),
+            },
+            groups: vec![Group {
+                rects: 0..1,
+                circles: 0..1,
+                filter: Filter::opacity(0.5),
+            }],
+            frame_filter: Filter {
+                grayscale: 1.0,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json_and_binary() {
+        let scene = populated();
+
+        let json = scene.to_json().unwrap();
+        assert_eq!(SceneFile::from_json(&json).unwrap(), scene);
+        assert_eq!(SceneFile::from_bytes(json.as_bytes()).unwrap(), scene);
+
+        let binary = scene.to_binary().unwrap();
+        assert!(binary.len() < json.len());
+        assert_eq!(SceneFile::from_binary(&binary).unwrap(), scene);
+        assert_eq!(SceneFile::from_bytes(&binary).unwrap(), scene);
+    }
+
+    #[test]
+    fn rejects_corrupt_and_truncated_files() {
+        let binary = populated().to_binary().unwrap();
+        for len in 0..binary.len() {
+            assert!(SceneFile::from_bytes(&binary[..len]).is_err());
+        }
+
+        let mut flipped = binary.clone();
+        flipped[MAGIC.len()] = 0xff;
+        assert!(matches!(
+            SceneFile::from_bytes(&flipped),
+            Err(SceneError::Corrupt(_))
+        ));
+
+        let json = populated().to_json().unwrap();
+        assert!(matches!(
+            SceneFile::from_json(&json[..json.len() / 2]),
+            Err(SceneError::Json(_))
+        ));
+        assert!(matches!(
+            SceneFile::from_bytes(&[0xff, 0xfe]),
+            Err(SceneError::Corrupt("not UTF-8"))
+        ));
+        assert!(matches!(
+            SceneFile::from_json(r#"{ "version": 3, "bracket": { "participants": [] } }"#),
+            Err(SceneError::UnknownVersion(3))
+        ));
+    }
+
+    #[test]
+    fn migrates_a_plain_tournament() {
+        let scene = SceneFile::from_json(
+            r#"{
+                "participants": ["Aurora", "Blizzard"],
+                "matches": [{ "round": 0, "index": 0, "scores": [3, 1] }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.version, VERSION);
+        assert_eq!(scene.bracket.participants, ["Aurora", "Blizzard"]);
+        assert_eq!(scene.bracket.matches[0].scores, Some([3, 1]));
+        assert_eq!(scene.theme, Theme::default());
+        assert_eq!(scene.world, Primitives::default());
+    }
+
+    #[test]
+    fn migrates_the_background() {
+        let scene = SceneFile::from_json(
+            r#"{
+                "version": 1,
+                "bracket": { "participants": ["Aurora"] },
+                "background": [0.1, 0.1, 0.1, 1.0]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.version, VERSION);
+        assert_eq!(scene.theme.background, Color::new(0.1, 0.1, 0.1, 1.0));
+        // Version 1 had nothing else of the theme, so the rest keeps the defaults
+        assert_eq!(scene.theme.palette, Theme::default().palette);
+        assert_eq!(scene.theme.card, Theme::default().card);
+
+        // Without a background there is no theme to migrate
+        let scene =
+            SceneFile::from_json(r#"{ "version": 1, "bracket": { "participants": ["Aurora"] } }"#)
+                .unwrap();
+        assert_eq!(scene.theme, Theme::default());
+    }
+
+    #[test]
+    fn validates_references() {
+        let scene = populated();
+        assert!(scene.validate(0).is_ok());
+
+        let mut scene = populated();
+        scene.screen.circles.push(CircleData {
+            border_gradient: 2,
+            ..Default::default()
+        });
+        assert!(matches!(scene.validate(0), Err(SceneError::Invalid(_))));
+
+        let mut scene = populated();
+        scene.groups[0].rects = 9..10;
+        assert!(scene.validate(0).is_err());
+        assert!(scene.validate(9).is_ok());
+        // Only the world rects after the bracket can be grouped
+        scene.groups[0].rects = 0..10;
+        assert!(scene.validate(9).is_err());
+
+        let mut scene = populated();
+        scene.gradients = vec![scene.gradients[0].clone(); MAX_GRADIENTS + 1];
+        assert!(scene.validate(0).is_err());
+    }
+}
@@ -1,93 +1,181 @@
 use cgmath::num_traits::ToPrimitive;
-use cgmath::vec2;
+use cgmath::{vec2, Vector2};
+use instant::Instant;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
 use wgpu::util::DeviceExt;
 use winit::dpi::PhysicalPosition;
-use winit::event::{ElementState, MouseButton, MouseScrollDelta};
-use winit::{event::WindowEvent, window::Window};
+use winit::event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode};
+use winit::{
+    event::WindowEvent,
+    window::{Fullscreen, Window},
+};
 
-use crate::camera::{Camera, CameraUniform, Limits};
-use crate::circle::CirclePipeline;
-use crate::rect::RectPipeline;
+use crate::animation::{Animation, AnimationId, Animator, Scene};
+use crate::bracket::{Bracket, BracketView, MatchId, ParticipantId, Tournament};
+use crate::camera::{Camera, CameraUniform, Limits, Space};
+use crate::circle::{Circle, CirclePipeline};
+use crate::clock::{FrameClock, FrameStats};
+use crate::color::Color;
+use crate::compositor::{ungrouped, Compositor, Filter, Group, Source};
+use crate::export::{Drawing, Region, Shape};
+use crate::gpu::{Gpu, InitError};
+use crate::gradient::{Gradient, GradientId, Gradients};
+use crate::overlay::DebugOverlay;
+use crate::popover::Popover;
+use crate::rect::{Rect, RectPipeline};
+use crate::scene_file::{
+    CameraData, CircleData, Primitives, RectData, SceneError, SceneFile, VERSION,
+};
+use crate::theme::{RoleMap, Roles, Theme};
+use crate::ui::{Style, Ui};
+
+/// Most the cursor may move between press and release for a click, in pixels
+const CLICK_DISTANCE: f64 = 4.0;
+/// World units per pixel the user can zoom between
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 10.0;
+/// Top left corner of the first match
+const BRACKET_ORIGIN: [f32; 2] = [40.0, 40.0];
+/// Space around the content in exports of `Region::Content`, in world units
+const EXPORT_MARGIN: f32 = 20.0;
+/// Largest side of the tiles PNG exports are drawn in, in pixels
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_TILE: u32 = 1024;
+/// Widest blur the compositor draws, in pixels, see `fs_blur`
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_FILTER_BLUR: f32 = 64.0;
+
+/// Something the user did in the scene, collected for embedders like the web viewer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ViewerEvent {
+    MatchClicked(MatchId),
+    /// The participant under the cursor changed, `None` when the cursor left them
+    ParticipantHovered(Option<ParticipantId>),
+}
 
 pub struct State {
     surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    gpu: Rc<Gpu>,
+    /// The last frame found the surface lost, a reconfigure didn't bring it back yet
+    surface_lost: bool,
     config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
+    /// Physical pixels per logical pixel, screen space is laid out in logical pixels
+    scale_factor: f64,
+    fullscreen: bool,
+    /// Fullscreen change waiting for `apply_fullscreen`, the state doesn't own the window
+    fullscreen_request: Option<bool>,
 
-    clear_color: wgpu::Color,
+    theme: Theme,
 
     camera: Camera,
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
     camera_bind_group: wgpu::BindGroup,
+    /// Maps world units to logical pixels, for everything drawn over the scene
+    screen_camera_uniform: CameraUniform,
+    screen_camera_buffer: wgpu::Buffer,
+    screen_camera_bind_group: wgpu::BindGroup,
+
+    sample_count: u32,
+    /// Multisampled color target that gets resolved into the surface, `None` without MSAA
+    msaa_framebuffer: Option<wgpu::TextureView>,
+
+    gradients: Gradients,
+
+    bracket: Bracket,
+    /// Rects of the bracket, they start at the first rect instance
+    bracket_view: BracketView,
+    /// Participant whose path is highlighted
+    hovered: Option<ParticipantId>,
+    /// Not yet collected with `take_events`
+    events: Vec<ViewerEvent>,
 
     rect_pipeline: RectPipeline,
     circle_pipeline: CirclePipeline,
+    /// Primitives in `Space::Screen`, drawn after the world
+    screen_rect_pipeline: RectPipeline,
+    screen_circle_pipeline: CirclePipeline,
+    /// Theme colors of rects by space and index, world indices count from the first rect
+    /// after the bracket
+    rect_roles: RoleMap,
+    circle_roles: RoleMap,
+
+    compositor: Compositor,
+    /// Rect ranges count from the first world rect after the bracket like `rect_roles`, so a
+    /// bracket with more or fewer rects doesn't move them
+    groups: Vec<Group>,
+    /// Applied to the whole frame after the scene is drawn
+    frame_filter: Filter,
+
+    /// Details of the clicked match, with the match it belongs to
+    popover: Option<(MatchId, Popover)>,
+    /// Draws the popover in screen space
+    popover_pipeline: RectPipeline,
+    /// The popover, the camera or the screen changed since the popover rects were built
+    popover_stale: bool,
+    /// Toolbar in screen space, rebuilt every frame
+    ui: Ui,
+    /// Selected entry of the MSAA dropdown
+    msaa_option: usize,
+    /// Toggled with F3
+    overlay: DebugOverlay,
 
     last_cursor_position: PhysicalPosition<f64>,
+    /// Where the left button went down, a release close to it is a click
+    press_position: PhysicalPosition<f64>,
     mouse_pressed: bool,
+
+    /// Something changed since the last frame
+    dirty: bool,
+    /// Earliest time a pending animation needs the next frame
+    redraw_at: Option<Instant>,
+    /// Set while every animation waits out its delay, since when the loop sleeps for them
+    waiting_since: Option<Instant>,
+    clock: FrameClock,
+    animator: Animator,
 }
 
 impl State {
-    pub async fn new(window: &Window) -> Self {
-        let size = window.inner_size();
+    /// Creates a device for this window alone
+    pub async fn new(window: &Window) -> Result<Self, InitError> {
+        let (gpu, surface) = Gpu::new(window).await?;
+        Self::with_surface(window, Rc::new(gpu), surface)
+    }
 
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+    /// Draws with the device of another state, see `gpu`
+    pub fn with_gpu(window: &Window, gpu: Rc<Gpu>) -> Result<Self, InitError> {
+        let surface = gpu.create_surface(window);
+        Self::with_surface(window, gpu, surface)
+    }
+
+    fn with_surface(
+        window: &Window,
+        gpu: Rc<Gpu>,
+        surface: wgpu::Surface,
+    ) -> Result<Self, InitError> {
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor();
+        let device = &gpu.device;
+        let format = surface.get_preferred_format(&gpu.adapter).ok_or_else(|| {
+            InitError::NoSurfaceFormat {
+                adapter: gpu.adapter.get_info().name,
+            }
+        })?;
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(&adapter).unwrap(),
+            format,
             width: size.width,
             height: size.height,
             present_mode: wgpu::PresentMode::Fifo,
         };
-        surface.configure(&device, &config);
-
-        let clear_color = wgpu::Color {
-            r: 0.0,
-            g: 0.0,
-            b: 0.0,
-            a: 1.0,
-        };
+        surface.configure(device, &config);
 
-        let camera = Camera {
-            offset: vec2(0.0, 0.0),
-            zoom: 1.0,
-            mouse_pos: vec2(0.0, 0.0),
-            limits: crate::camera::Limits {
-                left: 0.0,
-                right: config.width as f32,
-                bottom: config.height as f32,
-                top: 0.0,
-            },
-        };
+        let camera = Camera::screen(size, 1.0);
 
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera);
@@ -122,27 +210,97 @@ impl State {
             label: Some("camera_bind_group"),
         });
 
-        let rect_pipeline = RectPipeline::new(&device, &camera_bind_group_layout, &config);
-        let circle_pipeline = CirclePipeline::new(&device, &camera_bind_group_layout, &config);
+        let mut screen_camera_uniform = CameraUniform::new();
+        screen_camera_uniform.update_view_proj(&Camera::screen(size, scale_factor));
+
+        let screen_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Screen Camera Buffer"),
+            contents: bytemuck::cast_slice(&[screen_camera_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let screen_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_camera_buffer.as_entire_binding(),
+            }],
+            label: Some("screen_camera_bind_group"),
+        });
+
+        let sample_count = 1;
+        let msaa_framebuffer = create_msaa_framebuffer(device, &config, sample_count);
+
+        let gradients = Gradients::new(device, config.format);
+
+        let bind_group_layouts = [&camera_bind_group_layout, &gradients.bind_group_layout];
+        let rect_pipeline = RectPipeline::new(device, &bind_group_layouts, &config, sample_count);
+        let circle_pipeline =
+            CirclePipeline::new(device, &bind_group_layouts, &config, sample_count);
+
+        let theme = Theme::default();
+        let bracket = Bracket::default();
+        let bracket_view = BracketView::new(&bracket, BRACKET_ORIGIN, &theme, config.format);
+
+        let compositor = Compositor::new(device, &config);
+        // Screen space layers are drawn straight onto the surface, without MSAA
+        let screen_rect_pipeline = RectPipeline::new(device, &bind_group_layouts, &config, 1);
+        let screen_circle_pipeline = CirclePipeline::new(device, &bind_group_layouts, &config, 1);
+        let popover_pipeline = RectPipeline::new(device, &bind_group_layouts, &config, 1);
+        let ui = Ui::new(device, &bind_group_layouts, &config, scale_factor, &theme);
+        let overlay = DebugOverlay::new(device, &bind_group_layouts, &config);
 
         let last_cursor_position = PhysicalPosition::new(0.0, 0.0);
 
-        Self {
+        Ok(Self {
             surface,
-            device,
-            queue,
+            gpu,
+            surface_lost: false,
             config,
             size,
-            clear_color,
+            scale_factor,
+            fullscreen: false,
+            fullscreen_request: None,
+            theme,
             camera,
+            camera_bind_group_layout,
             camera_bind_group,
             camera_buffer,
             camera_uniform,
+            screen_camera_uniform,
+            screen_camera_buffer,
+            screen_camera_bind_group,
+            sample_count,
+            msaa_framebuffer,
+            gradients,
+            bracket,
+            bracket_view,
+            hovered: None,
+            events: Vec::new(),
             rect_pipeline,
             circle_pipeline,
+            screen_rect_pipeline,
+            screen_circle_pipeline,
+            rect_roles: RoleMap::default(),
+            circle_roles: RoleMap::default(),
+            compositor,
+            groups: Vec::new(),
+            frame_filter: Filter::default(),
+            popover: None,
+            popover_pipeline,
+            popover_stale: true,
+            ui,
+            msaa_option: 0,
+            overlay,
             last_cursor_position,
+            press_position: last_cursor_position,
             mouse_pressed: false,
-        }
+            dirty: true,
+            redraw_at: None,
+            waiting_since: None,
+            clock: FrameClock::new(),
+            animator: Animator::new(),
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -153,73 +311,932 @@ impl State {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.surface.configure(&self.gpu.device, &self.config);
+            self.msaa_framebuffer =
+                create_msaa_framebuffer(&self.gpu.device, &self.config, self.sample_count);
+            self.compositor.resize(&self.gpu.device, &self.config);
+            self.update_screen_camera();
+            self.dirty = true;
 
-            self.camera.limits.bottom += height_diff as f32 * self.camera.zoom;
-            self.camera.limits.right += width_diff as f32 * self.camera.zoom;
+            // Grows evenly on all sides, so the world point in the center stays put
+            // e.g. when going fullscreen
+            self.camera.limits.left -= width_diff * self.camera.zoom / 2.0;
+            self.camera.limits.right += width_diff * self.camera.zoom / 2.0;
+            self.camera.limits.top -= height_diff * self.camera.zoom / 2.0;
+            self.camera.limits.bottom += height_diff * self.camera.zoom / 2.0;
 
-            self.camera_uniform.update_view_proj(&mut self.camera);
-            self.queue.write_buffer(
-                &self.camera_buffer,
-                0,
-                bytemuck::cast_slice(&[self.camera_uniform]),
-            );
+            self.update_camera();
         }
     }
 
+    /// Takes the place of `fresh`, a state for the same window on the device recreated after
+    /// this one was lost, see `Gpu::is_lost`. The scene, the camera and the toolbar carry
+    /// over and are uploaded to the new device, so drawing continues where it stopped.
+    pub fn recover(&mut self, fresh: Self) {
+        let old = std::mem::replace(self, fresh);
+
+        self.fullscreen = old.fullscreen;
+        self.fullscreen_request = old.fullscreen_request;
+        self.theme = old.theme;
+        self.ui
+            .set_style(Style::new(&self.theme, self.config.format));
+        self.bracket = old.bracket;
+        self.bracket_view = old.bracket_view;
+        self.hovered = old.hovered;
+        self.events = old.events;
+        self.groups = old.groups;
+        self.frame_filter = old.frame_filter;
+        self.set_popover(old.popover);
+        self.overlay.visible = old.overlay.visible;
+        self.last_cursor_position = old.last_cursor_position;
+        self.press_position = old.press_position;
+        self.mouse_pressed = old.mouse_pressed;
+        self.clock = old.clock;
+        self.animator = old.animator;
+        self.waiting_since = old.waiting_since;
+
+        for gradient in old.gradients.iter() {
+            self.gradients.add(&self.gpu.queue, gradient.clone());
+        }
+        self.set_rects(Space::World, old.rect_pipeline.instances().to_vec());
+        self.set_circles(Space::World, old.circle_pipeline.instances().to_vec());
+        self.set_rects(Space::Screen, old.screen_rect_pipeline.instances().to_vec());
+        self.set_circles(
+            Space::Screen,
+            old.screen_circle_pipeline.instances().to_vec(),
+        );
+        self.rect_roles = old.rect_roles;
+        self.circle_roles = old.circle_roles;
+        self.msaa_option = old.msaa_option;
+        self.set_sample_count(old.sample_count);
+
+        self.camera = old.camera;
+        self.update_camera();
+    }
+
+    /// Reconfigures the surface after `render` found it lost. Losing it again right away
+    /// means the device is gone with it.
+    pub fn surface_lost(&mut self) {
+        self.gpu.mark_surface_failed();
+        if self.surface_lost {
+            self.gpu.mark_lost();
+        }
+        self.surface_lost = true;
+        self.resize(self.size);
+        self.request_redraw();
+    }
+
+    /// Device to share with the states of other windows
+    pub fn gpu(&self) -> &Rc<Gpu> {
+        &self.gpu
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Requests borderless fullscreen or windowed mode, the event loop applies it
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen_request = Some(fullscreen);
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.set_fullscreen(!self.fullscreen);
+    }
+
+    /// Applies a pending fullscreen request. On the web this uses the Fullscreen API on the
+    /// canvas, which the browser only allows right after user input. The surface is resized
+    /// through the `Resized` event that follows.
+    pub fn apply_fullscreen(&mut self, window: &Window) {
+        // The user may have left fullscreen through the OS or the browser
+        self.fullscreen = window.fullscreen().is_some();
+
+        if let Some(fullscreen) = self.fullscreen_request.take() {
+            if fullscreen != self.fullscreen {
+                window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+                self.fullscreen = fullscreen;
+            }
+        }
+    }
+
+    /// Called when the window moves to a display with a different DPI, followed by a resize
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.ui.set_scale_factor(scale_factor);
+        self.update_screen_camera();
+    }
+
+    fn update_screen_camera(&mut self) {
+        self.screen_camera_uniform
+            .update_view_proj(&Camera::screen(self.size, self.scale_factor));
+        self.gpu.queue.write_buffer(
+            &self.screen_camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.screen_camera_uniform]),
+        );
+        self.popover_stale = true;
+        self.dirty = true;
+    }
+
+    pub fn rects(&self, space: Space) -> &[Rect] {
+        match space {
+            Space::World => self.rect_pipeline.instances(),
+            Space::Screen => self.screen_rect_pipeline.instances(),
+        }
+    }
+
+    /// Index of the topmost rect of `space` under `point`, given in the units of that space
+    pub fn rect_at(&self, space: Space, point: Vector2<f32>) -> Option<usize> {
+        match space {
+            Space::World => self.rect_pipeline.hit_test(point),
+            Space::Screen => self.screen_rect_pipeline.hit_test(point),
+        }
+    }
+
+    /// Replaces the rects of one space. The bracket is laid out at the start of the world rects.
+    /// The new rects have no roles.
+    pub fn set_rects(&mut self, space: Space, rects: Vec<Rect>) {
+        self.rect_roles.clear_space(space);
+        match space {
+            Space::World => self.rect_pipeline.set_instances(&self.gpu.device, rects),
+            Space::Screen => self
+                .screen_rect_pipeline
+                .set_instances(&self.gpu.device, rects),
+        }
+        self.dirty = true;
+    }
+
+    pub fn circles(&self, space: Space) -> &[Circle] {
+        match space {
+            Space::World => self.circle_pipeline.instances(),
+            Space::Screen => self.screen_circle_pipeline.instances(),
+        }
+    }
+
+    /// Replaces the circles of one space, the new circles have no roles
+    pub fn set_circles(&mut self, space: Space, circles: Vec<Circle>) {
+        self.circle_roles.clear_space(space);
+        match space {
+            Space::World => self
+                .circle_pipeline
+                .set_instances(&self.gpu.device, circles),
+            Space::Screen => self
+                .screen_circle_pipeline
+                .set_instances(&self.gpu.device, circles),
+        }
+        self.dirty = true;
+    }
+
+    /// Makes a gradient available to rects and circles, `None` if the table is full
+    pub fn add_gradient(&mut self, gradient: Gradient) -> Option<GradientId> {
+        let id = self.gradients.add(&self.gpu.queue, gradient)?;
+        self.dirty = true;
+        Some(id)
+    }
+
+    /// Replaces a gradient, `false` if there is none with that id
+    pub fn set_gradient(&mut self, id: GradientId, gradient: Gradient) -> bool {
+        if !self.gradients.set(&self.gpu.queue, id, gradient) {
+            return false;
+        }
+        self.dirty = true;
+        true
+    }
+
+    pub fn gradient(&self, id: GradientId) -> Option<&Gradient> {
+        self.gradients.get(id)
+    }
+
+    /// Cursor position in world units or logical pixels
+    pub fn cursor(&self, space: Space) -> Vector2<f32> {
+        match space {
+            Space::World => self
+                .camera_uniform
+                .get_absolute_mouse_pos(&self.camera, self.size),
+            Space::Screen => self.camera.mouse_pos / self.scale_factor as f32,
+        }
+    }
+
+    /// Sets the MSAA sample count, 1 disables multisampling. WebGL2 only supports 1 and 4.
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        if sample_count == self.sample_count {
+            return;
+        }
+
+        self.sample_count = sample_count;
+        self.dirty = true;
+        self.msaa_framebuffer =
+            create_msaa_framebuffer(&self.gpu.device, &self.config, sample_count);
+
+        let bind_group_layouts = [
+            &self.camera_bind_group_layout,
+            &self.gradients.bind_group_layout,
+        ];
+        self.rect_pipeline.set_sample_count(
+            &self.gpu.device,
+            &bind_group_layouts,
+            &self.config,
+            sample_count,
+        );
+        self.circle_pipeline.set_sample_count(
+            &self.gpu.device,
+            &bind_group_layouts,
+            &self.config,
+            sample_count,
+        );
+    }
+
+    /// Adds a group that is composited as one unit and returns its index. Rect ranges index
+    /// `rects(Space::World)`, `None` if they take in rects of the bracket or any range goes past
+    /// the instances there are. Groups are drawn after every ungrouped instance, so a faded
+    /// card ends up above unfaded ones that were added after it.
+    pub fn add_group(&mut self, group: Group) -> Option<usize> {
+        if !group.fits(
+            self.rect_pipeline.instances().len() as u32,
+            self.circle_pipeline.instances().len() as u32,
+        ) {
+            return None;
+        }
+
+        let group = self.after_bracket(&group)?;
+        self.groups.push(group);
+        self.dirty = true;
+        Some(self.groups.len() - 1)
+    }
+
+    /// `false` if there is no group with that index
+    pub fn set_group_filter(&mut self, index: usize, filter: Filter) -> bool {
+        let group = match self.groups.get_mut(index) {
+            Some(group) => group,
+            None => return false,
+        };
+        group.filter = filter;
+        self.dirty = true;
+
+        true
+    }
+
+    pub fn remove_group(&mut self, index: usize) -> Option<Group> {
+        if index >= self.groups.len() {
+            return None;
+        }
+
+        self.dirty = true;
+        Some(self.groups.remove(index))
+    }
+
+    /// `group` with its rects counted from the first world rect after the bracket, `None` if
+    /// it takes in rects of the bracket
+    fn after_bracket(&self, group: &Group) -> Option<Group> {
+        let first = self.bracket_view.rects().len() as u32;
+        let rects = if group.rects.is_empty() {
+            0..0
+        } else if group.rects.start >= first {
+            group.rects.start - first..group.rects.end - first
+        } else {
+            return None;
+        };
+
+        Some(Group {
+            rects,
+            ..group.clone()
+        })
+    }
+
+    /// The groups behind the bracket as it is now, cut down to the instances there are.
+    /// Instances may have been removed since a group was added.
+    fn clamped_groups(&self) -> Vec<Group> {
+        let bracket_rects = self.bracket_view.rects().len() as u32;
+        let rects = self.rect_pipeline.instances().len() as u32;
+        let circles = self.circle_pipeline.instances().len() as u32;
+
+        self.groups
+            .iter()
+            .map(|group| group.offset_rects(bracket_rects).clamped(rects, circles))
+            .collect()
+    }
+
+    pub fn set_frame_filter(&mut self, filter: Filter) {
+        self.frame_filter = filter;
+        self.dirty = true;
+    }
+
+    pub fn bracket(&self) -> &Bracket {
+        &self.bracket
+    }
+
+    /// Shows `bracket` instead of the current one, world rects added after it are kept
+    pub fn set_bracket(&mut self, bracket: Bracket) {
+        self.bracket = bracket;
+        self.rebuild_bracket();
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Restyles the bracket, the popover, the toolbar, the background and the primitives with
+    /// roles. Other primitives keep their colors.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.apply_roles();
+        self.ui
+            .set_style(Style::new(&self.theme, self.config.format));
+        self.rebuild_bracket();
+    }
+
+    /// Draws the rect at `index` of `rects(space)` with colors of the theme, which follow
+    /// every `set_theme`. Colors without a role stay as they are. False if there is no such
+    /// rect, or it is one of the bracket, those follow the theme anyway.
+    pub fn set_rect_roles(&mut self, space: Space, index: usize, roles: Roles) -> bool {
+        let first = match space {
+            Space::World => self.bracket_view.rects().len(),
+            Space::Screen => 0,
+        };
+        if index < first || index >= self.rects(space).len() {
+            return false;
+        }
+
+        self.rect_roles.set(space, index - first, roles);
+        self.apply_roles();
+        true
+    }
+
+    /// Like `set_rect_roles` for the circle at `index` of `circles(space)`
+    pub fn set_circle_roles(&mut self, space: Space, index: usize, roles: Roles) -> bool {
+        if index >= self.circles(space).len() {
+            return false;
+        }
+
+        self.circle_roles.set(space, index, roles);
+        self.apply_roles();
+        true
+    }
+
+    /// Gives every primitive with roles the colors of the current theme
+    fn apply_roles(&mut self) {
+        let format = self.config.format;
+        let set = |target: &mut [f32; 4], color: Option<Color>| {
+            if let Some(color) = color {
+                *target = color.for_format(format);
+            }
+        };
+
+        let bracket_rects = self.bracket_view.rects().len();
+        for (space, index, roles) in self.rect_roles.iter() {
+            let rects = match space {
+                Space::World => &mut self.rect_pipeline.instances_mut()[bracket_rects..],
+                Space::Screen => self.screen_rect_pipeline.instances_mut(),
+            };
+            if let Some(rect) = rects.get_mut(index) {
+                let [fill, border, shadow] = roles.colors(&self.theme);
+                set(&mut rect.color, fill);
+                set(&mut rect.border_color, border);
+                set(&mut rect.shadow_color, shadow);
+            }
+        }
+        for (space, index, roles) in self.circle_roles.iter() {
+            let circles = match space {
+                Space::World => self.circle_pipeline.instances_mut(),
+                Space::Screen => self.screen_circle_pipeline.instances_mut(),
+            };
+            if let Some(circle) = circles.get_mut(index) {
+                let [fill, border, shadow] = roles.colors(&self.theme);
+                set(&mut circle.color, fill);
+                set(&mut circle.border_color, border);
+                set(&mut circle.shadow_color, shadow);
+            }
+        }
+
+        let queue = &self.gpu.queue;
+        self.rect_pipeline.write_instances(queue);
+        self.screen_rect_pipeline.write_instances(queue);
+        self.circle_pipeline.write_instances(queue);
+        self.screen_circle_pipeline.write_instances(queue);
+        self.dirty = true;
+    }
+
+    fn rebuild_bracket(&mut self) {
+        let old_rects = self.bracket_view.rects().len();
+        self.bracket_view = BracketView::new(
+            &self.bracket,
+            BRACKET_ORIGIN,
+            &self.theme,
+            self.config.format,
+        );
+
+        let mut rects = self.bracket_view.rects().to_vec();
+        rects.extend_from_slice(&self.rect_pipeline.instances()[old_rects..]);
+        self.rect_pipeline.set_instances(&self.gpu.device, rects);
+
+        // The new rects have no highlight, the participant under the cursor may have changed
+        self.hovered = None;
+        self.update_hover();
+        if let Some((id, _)) = self.popover {
+            self.set_popover(self.match_popover(id));
+        }
+        self.dirty = true;
+    }
+
+    /// The bracket, the primitives around it, their layers, the camera and the theme
+    pub fn save_scene(&self) -> SceneFile {
+        let format = self.config.format;
+        let primitives = |space: Space, rects: &[Rect], circles: &[Circle]| Primitives {
+            rects: (rects.iter().enumerate())
+                .map(|(i, r)| RectData {
+                    roles: self.rect_roles.get(space, i),
+                    ..RectData::from_rect(r, format)
+                })
+                .collect(),
+            circles: (circles.iter().enumerate())
+                .map(|(i, c)| CircleData {
+                    roles: self.circle_roles.get(space, i),
+                    ..CircleData::from_circle(c, format)
+                })
+                .collect(),
+        };
+        let bracket_rects = self.bracket_view.rects().len();
+
+        SceneFile {
+            version: VERSION,
+            theme: self.theme,
+            bracket: Tournament::from_bracket(&self.bracket),
+            camera: Some(CameraData {
+                center: self.camera.center().into(),
+                zoom: self.camera.zoom,
+            }),
+            gradients: self.gradients.iter().cloned().collect(),
+            world: primitives(
+                Space::World,
+                &self.rect_pipeline.instances()[bracket_rects..],
+                self.circle_pipeline.instances(),
+            ),
+            screen: primitives(
+                Space::Screen,
+                self.screen_rect_pipeline.instances(),
+                self.screen_circle_pipeline.instances(),
+            ),
+            // The file counts the rects of the bracket it was saved with
+            groups: (self.groups.iter())
+                .map(|group| group.offset_rects(bracket_rects as u32))
+                .collect(),
+            frame_filter: self.frame_filter,
+        }
+    }
+
+    /// Replaces everything in the scene with a saved one. Running animations stop, they
+    /// refer to primitives that are gone. A scene that doesn't pass `SceneFile::validate`
+    /// leaves everything as it was.
+    pub fn load_scene(&mut self, scene: &SceneFile) -> Result<(), SceneError> {
+        let format = self.config.format;
+
+        let bracket = scene.bracket.bracket();
+        let bracket_view = BracketView::new(&bracket, BRACKET_ORIGIN, &scene.theme, format);
+        scene.validate(bracket_view.rects().len())?;
+
+        self.animator = Animator::new();
+        self.set_popover(None);
+        self.gradients.clear(&self.gpu.queue);
+        for gradient in &scene.gradients {
+            self.gradients.add(&self.gpu.queue, gradient.clone());
+        }
+
+        // Keeps the old bracket in front of the new rects, `rebuild_bracket` replaces it
+        let bracket_rects = self.bracket_view.rects().len();
+        let mut rects = self.rect_pipeline.instances()[..bracket_rects].to_vec();
+        rects.extend(scene.world.rects.iter().map(|r| r.to_rect(format)));
+        self.rect_pipeline.set_instances(&self.gpu.device, rects);
+        let circles = scene.world.circles.iter().map(|c| c.to_circle(format));
+        self.set_circles(Space::World, circles.collect());
+
+        let rects = scene.screen.rects.iter().map(|r| r.to_rect(format));
+        self.set_rects(Space::Screen, rects.collect());
+        let circles = scene.screen.circles.iter().map(|c| c.to_circle(format));
+        self.set_circles(Space::Screen, circles.collect());
+
+        self.rect_roles.clear();
+        self.circle_roles.clear();
+        for (space, primitives) in [(Space::World, &scene.world), (Space::Screen, &scene.screen)] {
+            for (index, rect) in primitives.rects.iter().enumerate() {
+                self.rect_roles.set(space, index, rect.roles);
+            }
+            for (index, circle) in primitives.circles.iter().enumerate() {
+                self.circle_roles.set(space, index, circle.roles);
+            }
+        }
+
+        self.theme = scene.theme;
+        self.apply_roles();
+        self.ui.set_style(Style::new(&self.theme, format));
+        self.bracket = bracket;
+        self.rebuild_bracket();
+        // `validate` made sure none of them takes in rects of the bracket
+        let groups = scene.groups.iter().filter_map(|g| self.after_bracket(g));
+        self.groups = groups.collect();
+        self.frame_filter = scene.frame_filter;
+
+        match scene.camera {
+            Some(camera) => {
+                self.camera
+                    .look_at(camera.center.into(), camera.zoom, self.size);
+                self.update_camera();
+                self.update_hover();
+            }
+            None => self.fit_to_content(),
+        }
+
+        Ok(())
+    }
+
+    /// World area `region` covers in exports
+    fn export_bounds(&self, region: Region) -> Limits<f32> {
+        if region == Region::View {
+            return self.camera.limits;
+        }
+
+        let content = self
+            .rect_pipeline
+            .instances()
+            .iter()
+            .map(Rect::bounds)
+            .chain(self.circle_pipeline.instances().iter().map(Circle::bounds))
+            .reduce(|a, b| a.union(&b));
+        match content {
+            Some(content) => Limits {
+                left: content.left - EXPORT_MARGIN,
+                right: content.right + EXPORT_MARGIN,
+                bottom: content.bottom + EXPORT_MARGIN,
+                top: content.top - EXPORT_MARGIN,
+            },
+            None => self.camera.limits,
+        }
+    }
+
+    /// The world as exporters draw it, in drawing order. Shapes outside of `region` are
+    /// left out, apart from the text and lines of the bracket.
+    pub fn drawing(&self, region: Region) -> Drawing {
+        let format = self.config.format;
+        let rects = self.rect_pipeline.instances();
+        let circles = self.circle_pipeline.instances();
+
+        let bounds = self.export_bounds(region);
+
+        // Text and connectors of the bracket replace the rects they are drawn with,
+        // keyed by their first rect with the end of their rects
+        let mut compound: HashMap<usize, (usize, Shape)> = HashMap::new();
+        for label in self.bracket_view.labels() {
+            let Some(rect) = rects
+                .get(label.rects.start)
+                .filter(|_| !label.rects.is_empty())
+            else {
+                continue;
+            };
+            let shape = Shape::Text {
+                content: label.text.content.clone(),
+                position: label.text.position,
+                scale: label.text.scale,
+                // The current color, with the highlight of the hovered path
+                color: Color::from_format(rect.color, format),
+            };
+            compound.insert(label.rects.start, (label.rects.end, shape));
+        }
+        for line in self.bracket_view.connector_lines() {
+            let Some(rect) = rects
+                .get(line.rects.start)
+                .filter(|_| !line.rects.is_empty())
+            else {
+                continue;
+            };
+            let shape = Shape::Line {
+                points: line.points.to_vec(),
+                width: line.width,
+                color: Color::from_format(rect.color, format),
+            };
+            compound.insert(line.rects.start, (line.rects.end, shape));
+        }
+
+        // Same order as `render`, ungrouped instances first and then every group
+        let groups = self.clamped_groups();
+        let mut layers = vec![(
+            ungrouped(rects.len() as u32, groups.iter().map(|g| g.rects.clone())),
+            ungrouped(
+                circles.len() as u32,
+                groups.iter().map(|g| g.circles.clone()),
+            ),
+            1.0,
+        )];
+        for group in &groups {
+            layers.push((
+                vec![group.rects.clone()],
+                vec![group.circles.clone()],
+                group.filter.opacity,
+            ));
+        }
+
+        let mut shapes = Vec::new();
+        for (rect_ranges, circle_ranges, opacity) in layers {
+            for range in rect_ranges {
+                let mut index = range.start as usize;
+                while index < (range.end as usize).min(rects.len()) {
+                    if let Some((end, shape)) = compound.get(&index) {
+                        shapes.push(shape.clone().faded(opacity));
+                        index = *end;
+                        continue;
+                    }
+
+                    let rect = &rects[index];
+                    if rect.bounds().overlaps(&bounds) {
+                        let shape = Shape::Rect(RectData::from_rect(rect, format));
+                        shapes.push(shape.faded(opacity));
+                    }
+                    index += 1;
+                }
+            }
+
+            for range in circle_ranges {
+                let end = (range.end as usize).min(circles.len());
+                for circle in &circles[(range.start as usize).min(end)..end] {
+                    if circle.bounds().overlaps(&bounds) {
+                        let shape = Shape::Circle(CircleData::from_circle(circle, format));
+                        shapes.push(shape.faded(opacity));
+                    }
+                }
+            }
+        }
+
+        Drawing {
+            bounds,
+            background: self.theme.background,
+            gradients: self.gradients.iter().cloned().collect(),
+            shapes,
+        }
+    }
+
+    /// Renders `region` at `width` by `height` pixels into a PNG, for sizes far beyond the
+    /// window like posters. The image is drawn in tiles that fit the device and stitched row
+    /// by row, so only one row of tiles is in memory. Tiles are cut at whole pixels of the
+    /// image and overlap by the widest blur, which keeps edges and filters seamless.
+    ///
+    /// Only natively, reading back from the GPU has to wait for the browser on the web.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_png<W: std::io::Write>(
+        &self,
+        region: Region,
+        width: u32,
+        height: u32,
+        writer: W,
+    ) -> std::io::Result<W> {
+        use std::io::{Error, ErrorKind};
+
+        let format = self.config.format;
+        let bgra = match format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            _ => {
+                let message = format!("Can't export from {:?} surfaces", format);
+                return Err(Error::new(ErrorKind::Unsupported, message));
+            }
+        };
+        let mut png = crate::export::PngEncoder::new(writer, width, height)?;
+
+        // The shorter side grows, so the image isn't stretched
+        let bounds = self.export_bounds(region);
+        let unit = ((bounds.right - bounds.left) / width as f32)
+            .max((bounds.bottom - bounds.top) / height as f32);
+        let center = [
+            (bounds.left + bounds.right) / 2.0,
+            (bounds.top + bounds.bottom) / 2.0,
+        ];
+        let origin = [
+            center[0] - width as f32 * unit / 2.0,
+            center[1] - height as f32 * unit / 2.0,
+        ];
+
+        let blur = self
+            .groups
+            .iter()
+            .map(|group| group.filter.blur)
+            .fold(self.frame_filter.blur, f32::max);
+        let gutter = blur.ceil().clamp(0.0, MAX_FILTER_BLUR) as u32;
+        let device = &self.gpu.device;
+        let tile = device.limits().max_texture_dimension_2d.min(EXPORT_TILE);
+        // Pixels of the image each tile contributes
+        let step = tile - 2 * gutter;
+
+        let tile_config = wgpu::SurfaceConfiguration {
+            width: tile,
+            height: tile,
+            ..self.config.clone()
+        };
+        let compositor = Compositor::new(device, &tile_config);
+        let msaa_framebuffer = create_msaa_framebuffer(device, &tile_config, self.sample_count);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Export Tile"),
+            size: wgpu::Extent3d {
+                width: tile,
+                height: tile,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = (tile * 4).div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Export Readback Buffer"),
+            size: (padded_row * tile) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let stride = width as usize * 4;
+        let mut strip = vec![0; stride * step.min(height) as usize];
+        for y in (0..height).step_by(step as usize) {
+            let rows = step.min(height - y);
+
+            for x in (0..width).step_by(step as usize) {
+                let columns = step.min(width - x);
+                let corner = [
+                    origin[0] + (x as f32 - gutter as f32) * unit,
+                    origin[1] + (y as f32 - gutter as f32) * unit,
+                ];
+                let camera = Camera {
+                    mouse_pos: vec2(0.0, 0.0),
+                    limits: Limits {
+                        left: corner[0],
+                        right: corner[0] + tile as f32 * unit,
+                        bottom: corner[1] + tile as f32 * unit,
+                        top: corner[1],
+                    },
+                    zoom: unit,
+                    offset: vec2(0.0, 0.0),
+                };
+                let mut camera_uniform = CameraUniform::new();
+                camera_uniform.update_view_proj(&camera);
+                self.gpu.queue.write_buffer(
+                    &self.camera_buffer,
+                    0,
+                    bytemuck::cast_slice(&[camera_uniform]),
+                );
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Export Encoder"),
+                });
+                self.render_world(&mut encoder, &view, &compositor, msaa_framebuffer.as_ref());
+                encoder.copy_texture_to_buffer(
+                    texture.as_image_copy(),
+                    wgpu::ImageCopyBuffer {
+                        buffer: &buffer,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    wgpu::Extent3d {
+                        width: tile,
+                        height: tile,
+                        depth_or_array_layers: 1,
+                    },
+                );
+                self.gpu.queue.submit(std::iter::once(encoder.finish()));
+
+                let slice = buffer.slice(..);
+                let mapping = slice.map_async(wgpu::MapMode::Read);
+                device.poll(wgpu::Maintain::Wait);
+                pollster::block_on(mapping).map_err(Error::other)?;
+                if self.gpu.is_lost() {
+                    return Err(Error::other("Lost the graphics device"));
+                }
+
+                let data = slice.get_mapped_range();
+                for row in 0..rows as usize {
+                    let start = (row + gutter as usize) * padded_row as usize + gutter as usize * 4;
+                    let source = &data[start..start + columns as usize * 4];
+                    let start = row * stride + x as usize * 4;
+                    let destination = &mut strip[start..start + columns as usize * 4];
+                    destination.copy_from_slice(source);
+                    for pixel in destination.chunks_exact_mut(4) {
+                        if bgra {
+                            pixel.swap(0, 2);
+                        }
+                        unpremultiply(pixel, format);
+                    }
+                }
+                drop(data);
+                buffer.unmap();
+            }
+
+            png.write_rows(&strip[..rows as usize * stride])?;
+        }
+
+        // Back to what the window shows
+        self.gpu.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+
+        png.finish()
+    }
+
+    /// Zooms and pans so all world rects are in view
+    pub fn fit_to_content(&mut self) {
+        if let Some(bounds) = self.rect_pipeline.bounds() {
+            self.camera.fit(bounds, self.size);
+            self.update_camera();
+            self.update_hover();
+        }
+    }
+
+    /// Centers the card of match `id` and zooms in on it as far as the zoom range allows,
+    /// returns false if there is no such match
+    pub fn zoom_to_match(&mut self, id: MatchId) -> bool {
+        let Some(bounds) = self.bracket_view.card_bounds(id) else {
+            return false;
+        };
+
+        self.camera.fit(bounds, self.size);
+        self.camera
+            .set_zoom(self.camera.zoom.clamp(MIN_ZOOM, MAX_ZOOM), self.size);
+        self.update_camera();
+        self.update_hover();
+        true
+    }
+
+    /// Clicks and hovers since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<ViewerEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn input(&mut self, event: &WindowEvent) -> bool {
+        // Widgets get the input first, so clicking a button doesn't pan the camera
+        let consumed = self.ui.input(event);
+        if self.ui.needs_redraw() {
+            self.dirty = true;
+        }
+        if consumed {
+            return true;
+        }
+
         match event {
             WindowEvent::MouseWheel { delta, .. } => {
-                match delta {
-                    MouseScrollDelta::PixelDelta(PhysicalPosition { x: _, y }) => {
-                        let limits0 = &self.camera.limits;
-                        let w0 = self
-                            .camera_uniform
-                            .get_absolute_mouse_pos(&self.camera, self.size);
-                        let zoom_factor0 = self.camera.zoom;
-
-                        self.camera.zoom -= y.to_f32().unwrap() * 0.002;
-                        self.camera.zoom = self.camera.zoom.clamp(0.5, 10.0);
-
-                        let zoom_factor_ratio = self.camera.zoom / zoom_factor0;
-
-                        let left = w0.x - (w0.x - limits0.left) * zoom_factor_ratio;
-                        let right = w0.x - (w0.x - limits0.right) * zoom_factor_ratio;
-                        let bottom = w0.y - (w0.y - limits0.bottom) * zoom_factor_ratio;
-                        let top = w0.y - (w0.y - limits0.top) * zoom_factor_ratio;
-
-                        self.camera.limits = Limits {
-                            left,
-                            right,
-                            bottom,
-                            top,
-                        };
-
-                        self.camera_uniform.update_view_proj(&mut self.camera);
-                        self.queue.write_buffer(
-                            &self.camera_buffer,
-                            0,
-                            bytemuck::cast_slice(&[self.camera_uniform]),
-                        );
-                    }
-                    _ => {}
-                };
+                if let MouseScrollDelta::PixelDelta(PhysicalPosition { x: _, y }) = delta {
+                    let limits0 = &self.camera.limits;
+                    let w0 = self
+                        .camera_uniform
+                        .get_absolute_mouse_pos(&self.camera, self.size);
+                    let zoom_factor0 = self.camera.zoom;
+
+                    self.camera.zoom -= y.to_f32().unwrap() * 0.002;
+                    self.camera.zoom = self.camera.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
 
-                return true;
+                    let zoom_factor_ratio = self.camera.zoom / zoom_factor0;
+
+                    let left = w0.x - (w0.x - limits0.left) * zoom_factor_ratio;
+                    let right = w0.x - (w0.x - limits0.right) * zoom_factor_ratio;
+                    let bottom = w0.y - (w0.y - limits0.bottom) * zoom_factor_ratio;
+                    let top = w0.y - (w0.y - limits0.top) * zoom_factor_ratio;
+
+                    self.camera.limits = Limits {
+                        left,
+                        right,
+                        bottom,
+                        top,
+                    };
+
+                    self.update_camera();
+                    self.update_hover();
+                }
+
+                true
             }
 
             WindowEvent::MouseInput { button, state, .. } => {
                 if *button == MouseButton::Left {
-                    if *state == ElementState::Released {
+                    // Presses that went to the ui never set `mouse_pressed`
+                    if *state == ElementState::Released && self.mouse_pressed {
                         self.mouse_pressed = false;
+
+                        let dx = self.last_cursor_position.x - self.press_position.x;
+                        let dy = self.last_cursor_position.y - self.press_position.y;
+                        if dx.hypot(dy) <= CLICK_DISTANCE {
+                            self.click();
+                        }
                     }
 
                     if *state == ElementState::Pressed {
                         self.mouse_pressed = true;
+                        self.press_position = self.last_cursor_position;
                     }
                 }
 
-                return true;
+                true
             }
 
             WindowEvent::CursorMoved { position, .. } => {
@@ -239,68 +1256,573 @@ impl State {
                     self.camera.limits.bottom += difference.y * self.camera.zoom;
                     self.camera.limits.top += difference.y * self.camera.zoom;
 
-                    self.camera_uniform.update_view_proj(&mut self.camera);
-                    self.queue.write_buffer(
-                        &self.camera_buffer,
-                        0,
-                        bytemuck::cast_slice(&[self.camera_uniform]),
-                    );
+                    self.update_camera();
                 }
 
                 self.last_cursor_position = *position;
+                self.update_hover();
 
-                return true;
+                true
             }
-            _ => {
-                self.clear_color = wgpu::Color {
-                    r: 0.01,
-                    g: 0.01,
-                    b: 0.1,
-                    a: 1.0,
-                };
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                ..
+            } => {
+                self.overlay.visible = !self.overlay.visible;
+                self.dirty = true;
+
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                ..
+            } => {
+                self.toggle_fullscreen();
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks the frame as changed, it gets redrawn on the next loop iteration
+    pub fn request_redraw(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Requests a frame at `at`, for animations that need to continue later.
+    /// Keeps the earliest request if there are several.
+    pub fn request_redraw_at(&mut self, at: Instant) {
+        self.redraw_at = Some(match self.redraw_at {
+            Some(current) => current.min(at),
+            None => at,
+        });
+    }
+
+    /// `Some(now)` or earlier if a frame is due, a later time if an animation is waiting
+    /// and `None` if nothing needs to be drawn
+    pub fn next_redraw(&self) -> Option<Instant> {
+        if self.dirty {
+            return Some(Instant::now());
+        }
+
+        self.redraw_at
+    }
+
+    /// Opens the details of the clicked match, clicking it again or anywhere else closes them
+    fn click(&mut self) {
+        let cursor = self
+            .camera_uniform
+            .get_absolute_mouse_pos(&self.camera, self.size);
+        let clicked = self.bracket_view.match_at(cursor);
+        if let Some(id) = clicked {
+            self.events.push(ViewerEvent::MatchClicked(id));
+        }
+
+        let open = self.popover.as_ref().map(|(id, _)| *id);
+        let popover = match clicked {
+            Some(id) if open != Some(id) => self.match_popover(id),
+            _ => None,
+        };
+        self.set_popover(popover);
+        self.dirty = true;
+    }
+
+    /// Its rects are built on the next `update`
+    fn set_popover(&mut self, popover: Option<(MatchId, Popover)>) {
+        self.popover = popover;
+        self.popover_stale = true;
+    }
+
+    fn match_popover(&self, id: MatchId) -> Option<(MatchId, Popover)> {
+        let game = self.bracket.get(id)?;
+        let anchor = self.bracket_view.card_bounds(id)?;
+
+        let name = |slot: usize| {
+            game.participants[slot]
+                .and_then(|participant| self.bracket.name(participant))
+                .unwrap_or("TBD")
+        };
+        let score = |slot: usize| match game.scores[slot] {
+            Some(score) => score.to_string(),
+            None => "-".to_string(),
+        };
+
+        let mut lines = vec![format!(
+            "{} {} : {} {}",
+            name(0),
+            score(0),
+            score(1),
+            name(1)
+        )];
+        for (number, scores) in game.games.iter().enumerate() {
+            lines.push(format!(
+                "Game {}  {} : {}",
+                number + 1,
+                scores[0],
+                scores[1]
+            ));
+        }
+        if let Some(stream) = &game.stream {
+            lines.push(format!("Stream {}", stream));
+        }
+        if let Some(scheduled) = &game.scheduled {
+            lines.push(format!("Time {}", scheduled));
+        }
+
+        let title = format!("Round {} Match {}", id.round + 1, id.index + 1);
+        Some((id, Popover::new(anchor, title, lines)))
+    }
+
+    /// Highlights the path of the participant under the cursor
+    fn update_hover(&mut self) {
+        let cursor = self
+            .camera_uniform
+            .get_absolute_mouse_pos(&self.camera, self.size);
+        let hovered = self.bracket_view.participant_at(&self.bracket, cursor);
+        if hovered == self.hovered {
+            return;
+        }
+
+        self.hovered = hovered;
+        self.events.push(ViewerEvent::ParticipantHovered(hovered));
+        let rects = self.bracket_view.rects().len();
+        self.bracket_view.highlight(
+            &self.bracket,
+            hovered,
+            &mut self.rect_pipeline.instances_mut()[..rects],
+        );
+        self.rect_pipeline.write_instances(&self.gpu.queue);
+        self.dirty = true;
+    }
+
+    fn update_camera(&mut self) {
+        self.camera_uniform.update_view_proj(&self.camera);
+        self.gpu.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+        self.popover_stale = true;
+        self.dirty = true;
+    }
+
+    /// Uses a fixed simulation step for animations, `None` advances by the frame time
+    pub fn set_fixed_step(&mut self, step: Option<Duration>) {
+        self.clock.set_fixed_step(step);
+    }
+
+    pub fn frame_stats(&self) -> FrameStats {
+        self.clock.stats()
+    }
+
+    /// Starts an animation on the next frame, it keeps requesting frames until it finishes
+    pub fn animate(&mut self, animation: Animation) -> AnimationId {
+        self.dirty = true;
+        self.animator.add(animation)
+    }
+
+    /// Stops an animation, the animated properties keep their current values
+    pub fn cancel_animation(&mut self, id: AnimationId) {
+        self.animator.cancel(id);
+    }
+
+    pub fn update(&mut self) {
+        let waited = self.waiting_since.take().map(|since| since.elapsed());
+        // Nothing was animating, or animations only waited out their delays, so the time
+        // since the last frame was spent idle
+        if self.redraw_at.is_none() || waited.is_some() {
+            self.clock.resume();
+        }
+
+        // Whatever still animates asks for the next frame again while advancing
+        if self.redraw_at.is_some_and(|at| at <= Instant::now()) {
+            self.redraw_at = None;
+        }
+
+        let tick = self.clock.tick();
+        // The clock would clamp the time slept like a stall, delays count it in full
+        let mut animated = waited.is_some_and(|waited| self.advance(waited));
+        for _ in 0..tick.steps {
+            animated |= self.advance(tick.step);
+        }
 
-                return false;
+        if animated {
+            self.rect_pipeline.write_instances(&self.gpu.queue);
+            self.circle_pipeline.write_instances(&self.gpu.queue);
+        }
+        match self.animator.idle_time() {
+            Some(idle) if idle.is_zero() => self.request_redraw_at(Instant::now()),
+            // Sleeps until the first delay is over instead of drawing frames that look the same
+            Some(idle) => {
+                self.waiting_since = Some(Instant::now());
+                self.request_redraw_at(Instant::now() + idle);
             }
+            None => {}
+        }
+
+        self.build_ui();
+
+        // Follows its anchor through pan and zoom
+        if let Some((_, popover)) = self.popover.as_ref().filter(|_| self.popover_stale) {
+            let rects = popover.rects(
+                &self.camera,
+                self.size,
+                self.scale_factor,
+                &self.theme,
+                self.config.format,
+            );
+            self.popover_pipeline
+                .replace_instances(&self.gpu.device, &self.gpu.queue, rects);
+        }
+        self.popover_stale = false;
+
+        if self.overlay.visible {
+            let lines = self.debug_lines();
+            self.overlay.update(
+                &self.gpu.device,
+                &self.gpu.queue,
+                &lines,
+                self.clock.frame_times(),
+            );
+            self.request_redraw_at(Instant::now());
+        }
+    }
+
+    /// Declares the toolbar along the bottom edge and applies what was changed
+    fn build_ui(&mut self) {
+        let viewport = self.size.to_logical::<f32>(self.scale_factor);
+        let y = viewport.height - self.ui.style.height - 12.0;
+
+        self.ui.begin([viewport.width, viewport.height]);
+
+        let center = self.ui.button("Center", [12.0, y]);
+        let fullscreen = self.ui.button("Fullscreen", [108.0, y]);
+
+        let mut debug = self.overlay.visible;
+        let debug_changed = self.ui.toggle("Debug", [254.0, y], &mut debug);
+
+        let mut zoom = self.camera.zoom;
+        let zoom_changed =
+            self.ui
+                .slider("Zoom", [376.0, y], 160.0, &mut zoom, MIN_ZOOM..=MAX_ZOOM);
+
+        // Declared last, its open list covers the widgets next to it
+        let mut msaa_option = self.msaa_option;
+        let msaa_changed = self.ui.dropdown(
+            "MSAA",
+            [602.0, y],
+            140.0,
+            &mut msaa_option,
+            &["No MSAA", "MSAA 4x"],
+        );
+
+        self.ui.end(&self.gpu.device, &self.gpu.queue);
+
+        if center {
+            self.fit_to_content();
+        }
+        if fullscreen {
+            self.toggle_fullscreen();
+        }
+        if debug_changed {
+            self.overlay.visible = debug;
+        }
+        if zoom_changed {
+            self.camera.set_zoom(zoom, self.size);
+            self.update_camera();
         }
+        if msaa_changed {
+            self.msaa_option = msaa_option;
+            self.set_sample_count(if msaa_option == 1 { 4 } else { 1 });
+        }
+    }
+
+    /// Text shown by the debug overlay
+    fn debug_lines(&self) -> Vec<String> {
+        let stats = self.clock.stats();
+        let view = self.camera.limits;
+        let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+
+        let rects = self.rect_pipeline.instances();
+        let rects_visible = rects.iter().filter(|r| r.bounds().overlaps(&view)).count();
+        let circles = self.circle_pipeline.instances();
+        let circles_visible = circles
+            .iter()
+            .filter(|c| c.bounds().overlaps(&view))
+            .count();
+
+        let cursor = self
+            .camera_uniform
+            .get_absolute_mouse_pos(&self.camera, self.size);
+
+        vec![
+            format!("FPS {:.0}", stats.fps),
+            format!(
+                "FRAME {:.1} MS (MIN {:.1} MAX {:.1})",
+                ms(stats.average),
+                ms(stats.min),
+                ms(stats.max)
+            ),
+            // Nothing is culled, instances off screen are drawn as well
+            format!(
+                "RECTS {} IN VIEW {} OFF SCREEN",
+                rects_visible,
+                rects.len() - rects_visible
+            ),
+            format!(
+                "CIRCLES {} IN VIEW {} OFF SCREEN",
+                circles_visible,
+                circles.len() - circles_visible
+            ),
+            format!(
+                "BUFFERS RECTS {} KB CIRCLES {} KB",
+                self.rect_pipeline.instance_buffer_size() / 1024,
+                self.circle_pipeline.instance_buffer_size() / 1024
+            ),
+            format!("ZOOM {:.2}", self.camera.zoom),
+            format!(
+                "LIMITS L {:.0} R {:.0} T {:.0} B {:.0}",
+                view.left, view.right, view.top, view.bottom
+            ),
+            format!("CURSOR {:.0} {:.0}", cursor.x, cursor.y),
+        ]
     }
 
-    pub fn update(&mut self) {}
+    /// Moves everything time based forward by `dt`, returns whether instances changed
+    fn advance(&mut self, dt: Duration) -> bool {
+        let mut scene = Scene {
+            rects: self.rect_pipeline.instances_mut(),
+            circles: self.circle_pipeline.instances_mut(),
+        };
+
+        self.animator.advance(dt, &mut scene)
+    }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
+        self.surface_lost = false;
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = self
+            .gpu
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(self.clear_color),
-                    store: true,
-                },
-            }],
-            depth_stencil_attachment: None,
-        });
-
-        self.rect_pipeline
-            .render(&mut render_pass, &self.camera_bind_group);
+        self.render_world(
+            &mut encoder,
+            &view,
+            &self.compositor,
+            self.msaa_framebuffer.as_ref(),
+        );
 
-        self.circle_pipeline
-            .render(&mut render_pass, &self.camera_bind_group);
+        // Screen space goes on top of the world, after any frame filter
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screen Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
 
-        drop(render_pass);
+            let bind_groups = [&self.screen_camera_bind_group, &self.gradients.bind_group];
+            self.screen_rect_pipeline
+                .render(&mut render_pass, &bind_groups);
+            self.screen_circle_pipeline
+                .render(&mut render_pass, &bind_groups);
+            if self.popover.is_some() {
+                self.popover_pipeline.render(&mut render_pass, &bind_groups);
+            }
+            self.ui.render(&mut render_pass, &bind_groups);
+            self.overlay.render(&mut render_pass, &bind_groups);
+        }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.gpu.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
+        self.dirty = false;
+        // A frame got through, so whatever failed the surface before is over
+        if !self.gpu.is_lost() {
+            self.gpu.clear_surface_failed();
+        }
+
         Ok(())
     }
+
+    /// Draws the world with its groups and frame filter into `target`, seen through whatever
+    /// the camera buffer holds. `compositor` and `msaa_framebuffer` match the size of `target`.
+    fn render_world(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        compositor: &Compositor,
+        msaa_framebuffer: Option<&wgpu::TextureView>,
+    ) {
+        // With a frame filter the scene goes through an offscreen target first
+        let frame_filtered = !self.frame_filter.is_identity();
+        let scene_view = if frame_filtered {
+            &compositor.scene.view
+        } else {
+            target
+        };
+
+        let bind_groups = [&self.camera_bind_group, &self.gradients.bind_group];
+
+        let mut render_pass = begin_scene_pass(
+            encoder,
+            msaa_framebuffer,
+            scene_view,
+            self.theme.background.to_wgpu(self.config.format),
+        );
+
+        let groups = self.clamped_groups();
+        let rect_amount = self.rect_pipeline.instances().len() as u32;
+        for range in ungrouped(rect_amount, groups.iter().map(|g| g.rects.clone())) {
+            self.rect_pipeline
+                .render_range(&mut render_pass, &bind_groups, range);
+        }
+
+        let circle_amount = self.circle_pipeline.instances().len() as u32;
+        for range in ungrouped(circle_amount, groups.iter().map(|g| g.circles.clone())) {
+            self.circle_pipeline
+                .render_range(&mut render_pass, &bind_groups, range);
+        }
+
+        drop(render_pass);
+
+        for group in &groups {
+            let mut render_pass = begin_scene_pass(
+                encoder,
+                msaa_framebuffer,
+                &compositor.group.view,
+                wgpu::Color::TRANSPARENT,
+            );
+
+            self.rect_pipeline
+                .render_range(&mut render_pass, &bind_groups, group.rects.clone());
+            self.circle_pipeline.render_range(
+                &mut render_pass,
+                &bind_groups,
+                group.circles.clone(),
+            );
+
+            drop(render_pass);
+
+            compositor.apply(
+                &self.gpu.device,
+                encoder,
+                Source::Group,
+                scene_view,
+                &group.filter,
+            );
+        }
+
+        if frame_filtered {
+            // The target still holds last frames content, clear it before compositing
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Clear Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            compositor.apply(
+                &self.gpu.device,
+                encoder,
+                Source::Scene,
+                target,
+                &self.frame_filter,
+            );
+        }
+    }
+}
+
+/// Render pass for shapes, goes through `msaa_framebuffer` when multisampling is on
+fn begin_scene_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    msaa_framebuffer: Option<&'a wgpu::TextureView>,
+    target: &'a wgpu::TextureView,
+    clear_color: wgpu::Color,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+            view: msaa_framebuffer.unwrap_or(target),
+            resolve_target: msaa_framebuffer.map(|_| target),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear_color),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    })
+}
+
+fn create_msaa_framebuffer(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA Framebuffer"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Turns a premultiplied RGBA pixel as it was rendered into `format` into the straight alpha
+/// sRGB one PNG expects. sRGB formats premultiply before encoding, so the division happens on
+/// the linear values.
+#[cfg(not(target_arch = "wasm32"))]
+fn unpremultiply(pixel: &mut [u8], format: wgpu::TextureFormat) {
+    let alpha = pixel[3];
+    if alpha == 0 || alpha == 255 {
+        return;
+    }
+
+    let [r, g, b, a] =
+        Color::rgba(pixel[0], pixel[1], pixel[2], alpha as f32 / 255.0).for_format(format);
+    let straight = Color::from_format([r / a, g / a, b / a, a], format);
+    for (channel, value) in pixel.iter_mut().zip([straight.r, straight.g, straight.b]) {
+        *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
 }
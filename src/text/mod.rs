@@ -0,0 +1,95 @@
+use crate::rect::Rect;
+mod font;
+pub use font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// Horizontal space between two glyphs, in font pixels
+pub const LETTER_SPACING: u32 = 1;
+/// Vertical space between two lines, in font pixels
+pub const LINE_SPACING: u32 = 2;
+
+/// A block of bitmap text, drawn as rects so it needs no pipeline of its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub content: String,
+    /// Top left corner of the first line
+    pub position: [f32; 2],
+    /// Size of one font pixel, a glyph is 5 by 8 of these
+    pub scale: f32,
+    /// Straight alpha color in surface space, see `Color::for_format`
+    pub color: [f32; 4],
+}
+
+impl Text {
+    pub fn new(
+        content: impl Into<String>,
+        position: [f32; 2],
+        scale: f32,
+        color: [f32; 4],
+    ) -> Self {
+        Self {
+            content: content.into(),
+            position,
+            scale,
+            color,
+        }
+    }
+
+    /// Width of the longest line and height of all lines
+    pub fn size(&self) -> [f32; 2] {
+        let columns = self
+            .content
+            .lines()
+            .map(|line| line.chars().count() as u32)
+            .max()
+            .unwrap_or(0);
+        let lines = self.content.lines().count() as u32;
+
+        let width = (columns * (GLYPH_WIDTH + LETTER_SPACING)).saturating_sub(LETTER_SPACING);
+        let height = (lines * (GLYPH_HEIGHT + LINE_SPACING)).saturating_sub(LINE_SPACING);
+
+        [width as f32 * self.scale, height as f32 * self.scale]
+    }
+
+    /// One rect per vertical run of set pixels in each glyph column
+    pub fn rects(&self) -> Vec<Rect> {
+        let mut rects = Vec::new();
+
+        for (row, line) in self.content.lines().enumerate() {
+            let top =
+                self.position[1] + (row as u32 * (GLYPH_HEIGHT + LINE_SPACING)) as f32 * self.scale;
+
+            for (column, c) in line.chars().enumerate() {
+                let left = self.position[0]
+                    + (column as u32 * (GLYPH_WIDTH + LETTER_SPACING)) as f32 * self.scale;
+
+                for (x, bits) in glyph(c).iter().enumerate() {
+                    let mut y = 0;
+                    while y < GLYPH_HEIGHT {
+                        if bits & (1 << y) == 0 {
+                            y += 1;
+                            continue;
+                        }
+
+                        let start = y;
+                        while y < GLYPH_HEIGHT && bits & (1 << y) != 0 {
+                            y += 1;
+                        }
+
+                        let height = (y - start) as f32 * self.scale;
+                        rects.push(Rect {
+                            position: [
+                                left + (x as f32 + 0.5) * self.scale,
+                                top + start as f32 * self.scale + height / 2.0,
+                            ],
+                            size: [self.scale / 2.0, height / 2.0],
+                            color: self.color,
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+        }
+
+        rects
+    }
+}
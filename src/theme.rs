@@ -0,0 +1,445 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::camera::Space;
+use crate::color::Color;
+
+/// Everything that decides how the bracket, the popover and the toolbar look, switchable at
+/// runtime with `State::set_theme`. Fields missing from a theme file are the ones of the
+/// dark theme, so a file only needs what it changes:
+///
+/// ```json
+/// {
+///     "background": [0.1, 0.1, 0.1, 1.0],
+///     "card": { "corner_radius": 0.0 },
+///     "connector": { "width": 4.0 }
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Clear color of the world
+    pub background: Color,
+    pub palette: Palette,
+    pub card: CardStyle,
+    /// Name and score of the participant who won a match
+    pub winner: Color,
+    /// Name and score of the participant who lost it, undecided matches use `palette.text`
+    pub loser: Color,
+    pub connector: ConnectorStyle,
+    pub fonts: Fonts,
+}
+
+/// Colors the styles draw from
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    /// Fill of the match cards
+    pub surface: Color,
+    /// Background of the popover
+    pub overlay: Color,
+    /// Toolbar widgets at rest, under the cursor and while pressed
+    pub control: Color,
+    pub control_hovered: Color,
+    pub control_pressed: Color,
+    pub text: Color,
+    /// Less important text, like the details in the popover
+    pub muted: Color,
+    /// Filled parts of widgets, like an enabled toggle
+    pub accent: Color,
+    /// Path of the hovered participant and the widget with keyboard focus
+    pub highlight: Color,
+    pub shadow: Color,
+}
+
+/// Shape of the match cards, they are filled with `Palette::surface`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CardStyle {
+    /// Also the line between the two participants
+    pub border_color: Color,
+    pub border_width: f32,
+    pub corner_radius: f32,
+    /// Shadows take `Palette::shadow`, a transparent one turns them off
+    pub shadow_offset: [f32; 2],
+    pub shadow_blur: f32,
+}
+
+/// Lines from a match to the next one, the hovered path takes `Palette::highlight`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectorStyle {
+    pub color: Color,
+    pub width: f32,
+}
+
+/// Sizes of the bitmap font, as the size of one font pixel. A glyph is 5 by 8 of them.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fonts {
+    /// Names and scores, longer names are cut to fit bigger text
+    pub card: f32,
+    pub popover: f32,
+    pub toolbar: f32,
+}
+
+impl Theme {
+    /// Names `named` knows
+    pub const BUILT_IN: [&'static str; 3] = ["dark", "light", "high-contrast"];
+
+    pub fn dark() -> Self {
+        Self {
+            background: Color::rgb(25, 25, 89),
+            palette: Palette {
+                surface: Color::rgb(40, 40, 64),
+                overlay: Color::rgb(20, 20, 32).with_alpha(0.95),
+                control: Color::rgb(48, 48, 72),
+                control_hovered: Color::rgb(64, 64, 96),
+                control_pressed: Color::rgb(32, 32, 48),
+                text: Color::named("silver").unwrap(),
+                muted: Color::rgb(150, 150, 170),
+                accent: Color::rgb(30, 144, 255),
+                highlight: Color::named("gold").unwrap(),
+                shadow: Color::BLACK.with_alpha(0.5),
+            },
+            card: CardStyle {
+                border_color: Color::named("gray").unwrap(),
+                border_width: 2.0,
+                corner_radius: 6.0,
+                shadow_offset: [4.0, 4.0],
+                shadow_blur: 8.0,
+            },
+            winner: Color::WHITE,
+            loser: Color::rgb(128, 128, 150),
+            connector: ConnectorStyle {
+                color: Color::named("silver").unwrap(),
+                width: 2.0,
+            },
+            fonts: Fonts::default(),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            background: Color::rgb(236, 238, 243),
+            palette: Palette {
+                surface: Color::WHITE,
+                overlay: Color::WHITE.with_alpha(0.97),
+                control: Color::rgb(222, 226, 235),
+                control_hovered: Color::rgb(208, 214, 226),
+                control_pressed: Color::rgb(190, 196, 210),
+                text: Color::rgb(60, 64, 80),
+                muted: Color::rgb(110, 114, 130),
+                accent: Color::rgb(30, 144, 255),
+                highlight: Color::rgb(30, 144, 255),
+                shadow: Color::BLACK.with_alpha(0.15),
+            },
+            card: CardStyle {
+                border_color: Color::rgb(190, 194, 206),
+                ..Self::dark().card
+            },
+            winner: Color::rgb(16, 18, 28),
+            loser: Color::rgb(150, 154, 168),
+            connector: ConnectorStyle {
+                color: Color::rgb(150, 156, 172),
+                width: 2.0,
+            },
+            fonts: Fonts::default(),
+        }
+    }
+
+    /// Black and white with thick lines and no shadows, for projectors and low vision
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color::BLACK,
+            palette: Palette {
+                surface: Color::BLACK,
+                overlay: Color::BLACK,
+                control: Color::BLACK,
+                control_hovered: Color::rgb(64, 64, 64),
+                control_pressed: Color::rgb(128, 128, 128),
+                text: Color::WHITE,
+                muted: Color::WHITE,
+                accent: Color::rgb(255, 255, 0),
+                highlight: Color::rgb(0, 255, 255),
+                shadow: Color::TRANSPARENT,
+            },
+            card: CardStyle {
+                border_color: Color::WHITE,
+                border_width: 3.0,
+                corner_radius: 0.0,
+                shadow_offset: [0.0, 0.0],
+                shadow_blur: 0.0,
+            },
+            winner: Color::rgb(255, 255, 0),
+            loser: Color::rgb(200, 200, 200),
+            connector: ConnectorStyle {
+                color: Color::WHITE,
+                width: 3.0,
+            },
+            fonts: Fonts::default(),
+        }
+    }
+
+    /// Built-in theme by name, one of `BUILT_IN`
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Reads a theme file written like `to_json`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::from_json(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// The color `role` stands for in this theme
+    pub fn color(&self, role: Role) -> Color {
+        let palette = &self.palette;
+        match role {
+            Role::Background => self.background,
+            Role::Surface => palette.surface,
+            Role::Overlay => palette.overlay,
+            Role::Control => palette.control,
+            Role::ControlHovered => palette.control_hovered,
+            Role::ControlPressed => palette.control_pressed,
+            Role::Text => palette.text,
+            Role::Muted => palette.muted,
+            Role::Accent => palette.accent,
+            Role::Highlight => palette.highlight,
+            Role::Shadow => palette.shadow,
+            Role::CardBorder => self.card.border_color,
+            Role::Winner => self.winner,
+            Role::Loser => self.loser,
+            Role::Connector => self.connector.color,
+        }
+    }
+}
+
+/// A color of the theme, for primitives that should look like part of it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Background,
+    Surface,
+    Overlay,
+    Control,
+    ControlHovered,
+    ControlPressed,
+    Text,
+    Muted,
+    Accent,
+    Highlight,
+    Shadow,
+    CardBorder,
+    Winner,
+    Loser,
+    Connector,
+}
+
+/// Theme colors a rect or circle is drawn with instead of its own, set with
+/// `State::set_rect_roles` and looked up again on every `State::set_theme`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Roles {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Role>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<Role>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<Role>,
+}
+
+impl Roles {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill, border and shadow color in `theme`, `None` where the primitive keeps its own
+    pub fn colors(&self, theme: &Theme) -> [Option<Color>; 3] {
+        [self.fill, self.border, self.shadow].map(|role| role.map(|role| theme.color(role)))
+    }
+}
+
+/// Roles of the rects or of the circles of both spaces, by index. World rects count from the
+/// first rect after the bracket, so rebuilding the bracket doesn't move them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoleMap(HashMap<(Space, usize), Roles>);
+
+impl RoleMap {
+    /// Empty roles remove the entry
+    pub fn set(&mut self, space: Space, index: usize, roles: Roles) {
+        if roles.is_empty() {
+            self.0.remove(&(space, index));
+        } else {
+            self.0.insert((space, index), roles);
+        }
+    }
+
+    /// Empty for a primitive without roles
+    pub fn get(&self, space: Space, index: usize) -> Roles {
+        self.0.get(&(space, index)).copied().unwrap_or_default()
+    }
+
+    /// Forgets the roles of `space`, for when its primitives are replaced and the same
+    /// indices mean other primitives
+    pub fn clear_space(&mut self, space: Space) {
+        self.0.retain(|(other, _), _| *other != space);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Space, usize, Roles)> + '_ {
+        (self.0.iter()).map(|(&(space, index), &roles)| (space, index, roles))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Theme::dark().palette
+    }
+}
+
+impl Default for CardStyle {
+    fn default() -> Self {
+        Theme::dark().card
+    }
+}
+
+impl Default for ConnectorStyle {
+    fn default() -> Self {
+        Theme::dark().connector
+    }
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Self {
+            card: 2.0,
+            popover: 2.0,
+            toolbar: 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Role; 15] = [
+        Role::Background,
+        Role::Surface,
+        Role::Overlay,
+        Role::Control,
+        Role::ControlHovered,
+        Role::ControlPressed,
+        Role::Text,
+        Role::Muted,
+        Role::Accent,
+        Role::Highlight,
+        Role::Shadow,
+        Role::CardBorder,
+        Role::Winner,
+        Role::Loser,
+        Role::Connector,
+    ];
+
+    fn roles(fill: Option<Role>, border: Option<Role>, shadow: Option<Role>) -> Roles {
+        Roles {
+            fill,
+            border,
+            shadow,
+        }
+    }
+
+    #[test]
+    fn looks_up_every_role_in_every_theme() {
+        for name in Theme::BUILT_IN {
+            let theme = Theme::named(name).unwrap();
+            let palette = theme.palette;
+            let expected = [
+                theme.background,
+                palette.surface,
+                palette.overlay,
+                palette.control,
+                palette.control_hovered,
+                palette.control_pressed,
+                palette.text,
+                palette.muted,
+                palette.accent,
+                palette.highlight,
+                palette.shadow,
+                theme.card.border_color,
+                theme.winner,
+                theme.loser,
+                theme.connector.color,
+            ];
+
+            for (role, color) in ALL.into_iter().zip(expected) {
+                let colors = [
+                    roles(Some(role), None, None),
+                    roles(None, Some(role), None),
+                    roles(None, None, Some(role)),
+                ]
+                .map(|roles| roles.colors(&theme));
+                assert_eq!(colors[0], [Some(color), None, None], "{} {:?}", name, role);
+                assert_eq!(colors[1], [None, Some(color), None], "{} {:?}", name, role);
+                assert_eq!(colors[2], [None, None, Some(color)], "{} {:?}", name, role);
+            }
+        }
+
+        assert_eq!(Roles::default().colors(&Theme::dark()), [None; 3]);
+    }
+
+    #[test]
+    fn follows_the_theme() {
+        let roles = roles(Some(Role::Surface), Some(Role::Text), None);
+        let [dark, light] = [Theme::dark(), Theme::light()].map(|theme| roles.colors(&theme));
+        assert_ne!(dark, light);
+        assert_eq!(light[0], Some(Theme::light().palette.surface));
+    }
+
+    #[test]
+    fn forgets_the_roles_of_a_replaced_space() {
+        let accent = roles(Some(Role::Accent), None, None);
+        let mut map = RoleMap::default();
+        map.set(Space::World, 0, accent);
+        map.set(Space::World, 3, accent);
+        map.set(Space::Screen, 0, accent);
+
+        // The new world primitives at the same indices get none of the old roles
+        map.clear_space(Space::World);
+        assert!(map.get(Space::World, 0).is_empty());
+        assert!(map.get(Space::World, 3).is_empty());
+        assert_eq!(map.get(Space::Screen, 0), accent);
+        assert_eq!(map.iter().count(), 1);
+
+        // Empty roles don't leave an entry behind
+        map.set(Space::Screen, 0, Roles::default());
+        assert_eq!(map.iter().count(), 0);
+
+        map.set(Space::World, 1, accent);
+        map.clear();
+        assert_eq!(map, RoleMap::default());
+    }
+}
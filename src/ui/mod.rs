@@ -0,0 +1,284 @@
+use cgmath::{vec2, Vector2};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue, RenderPass, SurfaceConfiguration};
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+use crate::camera::Limits;
+use crate::circle::{Circle, CirclePipeline};
+use crate::rect::{Rect, RectPipeline};
+use crate::theme::Theme;
+mod style;
+mod widgets;
+pub use style::Style;
+
+/// Identifies a widget across frames, derived from its label
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WidgetId(u64);
+
+impl WidgetId {
+    pub fn new(label: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        label.hash(&mut hasher);
+        WidgetId(hasher.finish())
+    }
+}
+
+/// Immediate mode widgets in screen space. The widgets are declared again every frame
+/// between `begin` and `end`, input arrives in between through `input`.
+pub struct Ui {
+    pub style: Style,
+    rect_pipeline: RectPipeline,
+    circle_pipeline: CirclePipeline,
+    scale_factor: f64,
+    /// Size of the window in logical pixels, set every frame
+    viewport: [f32; 2],
+
+    /// In logical pixels
+    cursor: Vector2<f32>,
+    /// The left button went down or up since the last frame
+    pressed: bool,
+    released: bool,
+    keys: Vec<VirtualKeyCode>,
+
+    /// Widget the pointer went down on, until it is released
+    active: Option<WidgetId>,
+    /// Widget that receives the keyboard, changed with Tab or by clicking
+    focus: Option<WidgetId>,
+    open_dropdown: Option<WidgetId>,
+    needs_redraw: bool,
+
+    rects: Vec<Rect>,
+    circles: Vec<Circle>,
+    /// Drawn after all widgets, e.g. the options of an open dropdown
+    popup_rects: Vec<Rect>,
+    /// Areas covered by widgets, input over them doesn't reach the scene
+    areas: Vec<Limits<f32>>,
+    /// Focusable widgets in declaration order, for Tab
+    order: Vec<WidgetId>,
+}
+
+impl Ui {
+    /// `bind_group_layouts` are the camera and gradient layouts
+    pub fn new(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        config: &SurfaceConfiguration,
+        scale_factor: f64,
+        theme: &Theme,
+    ) -> Self {
+        Self {
+            style: Style::new(theme, config.format),
+            // Drawn straight onto the surface, so it never goes through the MSAA framebuffer
+            rect_pipeline: RectPipeline::new(device, bind_group_layouts, config, 1),
+            circle_pipeline: CirclePipeline::new(device, bind_group_layouts, config, 1),
+            scale_factor,
+            viewport: [0.0; 2],
+            cursor: vec2(-1.0, -1.0),
+            pressed: false,
+            released: false,
+            keys: Vec::new(),
+            active: None,
+            focus: None,
+            open_dropdown: None,
+            needs_redraw: true,
+            rects: Vec::new(),
+            circles: Vec::new(),
+            popup_rects: Vec::new(),
+            areas: Vec::new(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Restyles every widget from the next frame on
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+        self.needs_redraw = true;
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.needs_redraw = true;
+    }
+
+    /// Whether something changed that the next frame has to show
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn over_widget(&self) -> bool {
+        self.areas.iter().any(|area| contains(area, self.cursor))
+    }
+
+    /// Records input for the next frame. Returns true if the event was meant for the ui,
+    /// in which case it must not reach the camera.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(self.scale_factor);
+                let was_over = self.over_widget();
+                self.cursor = vec2(position.x, position.y);
+
+                // Hover states only change over widgets, dragging always moves something
+                if was_over || self.over_widget() || self.active.is_some() {
+                    self.needs_redraw = true;
+                }
+
+                // The scene still needs to know where the cursor is
+                false
+            }
+
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                let consumed = match state {
+                    ElementState::Pressed => {
+                        self.pressed = true;
+                        self.over_widget()
+                    }
+                    ElementState::Released => {
+                        self.released = true;
+                        self.active.is_some()
+                    }
+                };
+
+                if !consumed && *state == ElementState::Pressed {
+                    // Clicking the scene takes the focus away and closes dropdowns
+                    self.focus = None;
+                    self.open_dropdown = None;
+                }
+                self.needs_redraw = true;
+                consumed
+            }
+
+            WindowEvent::MouseWheel { .. } => self.over_widget(),
+
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                if *key == VirtualKeyCode::Tab {
+                    self.focus_next();
+                    self.needs_redraw = true;
+                    return true;
+                }
+
+                if *key == VirtualKeyCode::Escape
+                    && (self.focus.is_some() || self.open_dropdown.is_some())
+                {
+                    self.focus = None;
+                    self.open_dropdown = None;
+                    self.needs_redraw = true;
+                    return true;
+                }
+
+                let navigation = matches!(
+                    key,
+                    VirtualKeyCode::Return
+                        | VirtualKeyCode::Space
+                        | VirtualKeyCode::Left
+                        | VirtualKeyCode::Right
+                        | VirtualKeyCode::Up
+                        | VirtualKeyCode::Down
+                );
+                if navigation && self.focus.is_some() {
+                    self.keys.push(*key);
+                    self.needs_redraw = true;
+                    return true;
+                }
+
+                false
+            }
+
+            _ => false,
+        }
+    }
+
+    fn focus_next(&mut self) {
+        let next = match self
+            .focus
+            .and_then(|id| self.order.iter().position(|o| *o == id))
+        {
+            Some(index) => index + 1,
+            None => 0,
+        };
+        self.focus = self.order.get(next % self.order.len().max(1)).copied();
+    }
+
+    /// Starts declaring the widgets of a frame, `viewport` is the window size in logical pixels
+    pub fn begin(&mut self, viewport: [f32; 2]) {
+        self.viewport = viewport;
+        self.rects.clear();
+        self.circles.clear();
+        self.popup_rects.clear();
+        self.areas.clear();
+        self.order.clear();
+    }
+
+    /// Finishes the frame and uploads the widgets
+    pub fn end(&mut self, device: &Device, queue: &Queue) {
+        if self.released {
+            self.active = None;
+        }
+        self.pressed = false;
+        self.released = false;
+        self.keys.clear();
+        self.needs_redraw = false;
+
+        let mut rects = std::mem::take(&mut self.rects);
+        rects.append(&mut self.popup_rects);
+        self.rect_pipeline.replace_instances(device, queue, rects);
+        self.circle_pipeline
+            .replace_instances(device, queue, std::mem::take(&mut self.circles));
+    }
+
+    /// `bind_groups` are the screen camera and gradient bind groups
+    pub fn render<'a>(&'a self, render_pass: &mut RenderPass<'a>, bind_groups: &[&'a BindGroup]) {
+        self.rect_pipeline.render(render_pass, bind_groups);
+        self.circle_pipeline.render(render_pass, bind_groups);
+    }
+
+    /// Registers a widget covering `area` and returns whether the cursor is over it
+    fn interact(&mut self, id: WidgetId, area: Limits<f32>) -> Interaction {
+        self.areas.push(area);
+        self.order.push(id);
+
+        let hovered = contains(&area, self.cursor);
+        if hovered && self.pressed {
+            self.active = Some(id);
+            self.focus = Some(id);
+        }
+
+        let active = self.active == Some(id);
+        Interaction {
+            hovered,
+            active,
+            clicked: active && hovered && self.released,
+            focused: self.focus == Some(id),
+        }
+    }
+
+    fn key(&self, id: WidgetId, keys: &[VirtualKeyCode]) -> bool {
+        self.focus == Some(id) && self.keys.iter().any(|key| keys.contains(key))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Interaction {
+    hovered: bool,
+    /// The pointer went down on the widget and is still held
+    active: bool,
+    clicked: bool,
+    focused: bool,
+}
+
+fn contains(area: &Limits<f32>, point: Vector2<f32>) -> bool {
+    point.x >= area.left && point.x <= area.right && point.y >= area.top && point.y <= area.bottom
+}
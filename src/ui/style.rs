@@ -0,0 +1,38 @@
+use crate::theme::Theme;
+
+/// Colors and sizes of all widgets, colors are in surface space
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Style {
+    pub text: [f32; 4],
+    pub background: [f32; 4],
+    pub hovered: [f32; 4],
+    pub pressed: [f32; 4],
+    /// Filled parts, like an enabled toggle or the slider track up to the value
+    pub accent: [f32; 4],
+    /// Border of the widget that has keyboard focus
+    pub focus: [f32; 4],
+    pub text_scale: f32,
+    /// Height of buttons, toggles, sliders and dropdowns
+    pub height: f32,
+    pub padding: f32,
+    pub corner_radius: f32,
+}
+
+impl Style {
+    /// Colors and font size from `theme`, converted for `format`
+    pub fn new(theme: &Theme, format: wgpu::TextureFormat) -> Self {
+        let palette = &theme.palette;
+        Self {
+            text: palette.text.for_format(format),
+            background: palette.control.for_format(format),
+            hovered: palette.control_hovered.for_format(format),
+            pressed: palette.control_pressed.for_format(format),
+            accent: palette.accent.for_format(format),
+            focus: palette.highlight.for_format(format),
+            text_scale: theme.fonts.toolbar,
+            height: 28.0,
+            padding: 8.0,
+            corner_radius: 4.0,
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::ops::RangeInclusive;
+use winit::event::VirtualKeyCode;
+
+use super::{contains, Interaction, Ui, WidgetId};
+use crate::camera::Limits;
+use crate::circle::Circle;
+use crate::rect::Rect;
+use crate::text::{Text, GLYPH_HEIGHT};
+
+const TOGGLE_WIDTH: f32 = 44.0;
+const SLIDER_TRACK: f32 = 6.0;
+/// Part of the range a slider moves per arrow key
+const SLIDER_STEP: f32 = 0.05;
+/// Space between a widget and its label
+const LABEL_GAP: f32 = 8.0;
+
+const ACTIVATE: &[VirtualKeyCode] = &[VirtualKeyCode::Return, VirtualKeyCode::Space];
+
+impl Ui {
+    /// Text whose first line is vertically centered in a widget row at `position`
+    pub fn label(&mut self, text: &str, position: [f32; 2]) -> [f32; 2] {
+        let top =
+            position[1] + (self.style.height - GLYPH_HEIGHT as f32 * self.style.text_scale) / 2.0;
+        let text = Text::new(
+            text,
+            [position[0], top],
+            self.style.text_scale,
+            self.style.text,
+        );
+        self.rects.extend(text.rects());
+        text.size()
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        Text::new(text, [0.0; 2], self.style.text_scale, self.style.text).size()[0]
+    }
+
+    fn background(&self, area: Limits<f32>, interaction: Interaction) -> Rect {
+        let color = if interaction.active {
+            self.style.pressed
+        } else if interaction.hovered {
+            self.style.hovered
+        } else {
+            self.style.background
+        };
+
+        Rect {
+            position: area.center(),
+            size: area.half_size(),
+            color,
+            border_radius: [self.style.corner_radius; 4],
+            border: if interaction.focused { 2.0 } else { 0.0 },
+            border_color: self.style.focus,
+            ..Default::default()
+        }
+    }
+
+    fn row(&self, position: [f32; 2], width: f32) -> Limits<f32> {
+        Limits {
+            left: position[0],
+            right: position[0] + width,
+            bottom: position[1] + self.style.height,
+            top: position[1],
+        }
+    }
+
+    /// Returns true when clicked or activated with Enter or Space while focused
+    pub fn button(&mut self, label: &str, position: [f32; 2]) -> bool {
+        let id = WidgetId::new(label);
+        let width = self.text_width(label) + self.style.padding * 2.0;
+        let area = self.row(position, width);
+        let interaction = self.interact(id, area);
+
+        let background = self.background(area, interaction);
+        self.rects.push(background);
+        self.label(label, [position[0] + self.style.padding, position[1]]);
+
+        interaction.clicked || self.key(id, ACTIVATE)
+    }
+
+    /// A switch followed by its label, returns true when `value` changed
+    pub fn toggle(&mut self, label: &str, position: [f32; 2], value: &mut bool) -> bool {
+        let id = WidgetId::new(label);
+        let width = TOGGLE_WIDTH + LABEL_GAP + self.text_width(label);
+        let interaction = self.interact(id, self.row(position, width));
+
+        let changed = interaction.clicked || self.key(id, ACTIVATE);
+        if changed {
+            *value = !*value;
+        }
+
+        let radius = self.style.height / 2.0;
+        let mut track = self.background(self.row(position, TOGGLE_WIDTH), interaction);
+        track.border_radius = [radius; 4];
+        if *value {
+            track.color = self.style.accent;
+        }
+        self.rects.push(track);
+
+        let knob_x = if *value {
+            position[0] + TOGGLE_WIDTH - radius
+        } else {
+            position[0] + radius
+        };
+        self.circles.push(Circle {
+            position: [knob_x, position[1] + radius],
+            radius: radius - 4.0,
+            color: self.style.text,
+            ..Default::default()
+        });
+
+        self.label(label, [position[0] + TOGGLE_WIDTH + LABEL_GAP, position[1]]);
+        changed
+    }
+
+    /// Label followed by a track of `width`, dragged with the mouse or moved with the
+    /// arrow keys. Returns true when `value` changed.
+    pub fn slider(
+        &mut self,
+        label: &str,
+        position: [f32; 2],
+        width: f32,
+        value: &mut f32,
+        range: RangeInclusive<f32>,
+    ) -> bool {
+        let id = WidgetId::new(label);
+        let label_width = self.text_width(label) + LABEL_GAP;
+        let track_left = position[0] + label_width;
+        let interaction = self.interact(id, self.row(position, label_width + width));
+
+        let (min, max) = (*range.start(), *range.end());
+        let old = *value;
+
+        if interaction.active {
+            let amount = ((self.cursor.x - track_left) / width).clamp(0.0, 1.0);
+            *value = min + amount * (max - min);
+        }
+        if self.key(id, &[VirtualKeyCode::Left, VirtualKeyCode::Down]) {
+            *value = (*value - (max - min) * SLIDER_STEP).clamp(min, max);
+        }
+        if self.key(id, &[VirtualKeyCode::Right, VirtualKeyCode::Up]) {
+            *value = (*value + (max - min) * SLIDER_STEP).clamp(min, max);
+        }
+
+        // Values set from elsewhere may be outside the range, they are only shown clamped
+        let amount = if max > min {
+            ((*value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let center_y = position[1] + self.style.height / 2.0;
+
+        self.label(label, position);
+
+        let track = |width: f32, color| Rect {
+            position: [track_left + width / 2.0, center_y],
+            size: [width / 2.0, SLIDER_TRACK / 2.0],
+            color,
+            border_radius: [SLIDER_TRACK / 2.0; 4],
+            ..Default::default()
+        };
+        self.rects.push(track(width, self.style.background));
+        self.rects.push(track(width * amount, self.style.accent));
+
+        let knob_color = if interaction.hovered || interaction.active {
+            self.style.text
+        } else {
+            self.style.hovered
+        };
+        self.circles.push(Circle {
+            position: [track_left + width * amount, center_y],
+            radius: self.style.height / 3.0,
+            color: knob_color,
+            border: if interaction.focused { 2.0 } else { 0.0 },
+            border_color: self.style.focus,
+            ..Default::default()
+        });
+
+        *value != old
+    }
+
+    /// Shows `options[*selected]`, clicking opens the list of all options below or above it.
+    /// Up and Down change the selection while focused. Returns true when it changed.
+    pub fn dropdown(
+        &mut self,
+        label: &str,
+        position: [f32; 2],
+        width: f32,
+        selected: &mut usize,
+        options: &[&str],
+    ) -> bool {
+        let id = WidgetId::new(label);
+        let area = self.row(position, width);
+        let interaction = self.interact(id, area);
+        let old = *selected;
+
+        let mut open = self.open_dropdown == Some(id);
+        if interaction.clicked || self.key(id, ACTIVATE) {
+            open = !open;
+        }
+        if self.key(id, &[VirtualKeyCode::Up]) {
+            *selected = selected.saturating_sub(1);
+        }
+        if self.key(id, &[VirtualKeyCode::Down]) {
+            *selected += 1;
+        }
+        *selected = (*selected).min(options.len().saturating_sub(1));
+
+        let background = self.background(area, interaction);
+        self.rects.push(background);
+        let current = options.get(*selected).copied().unwrap_or("");
+        self.label(current, [position[0] + self.style.padding, position[1]]);
+        let arrow_x = area.right - self.style.padding - self.text_width("v");
+        self.label("v", [arrow_x, position[1]]);
+
+        if open {
+            let mut clicked_option = false;
+
+            // Opens upwards when the list doesn't fit below
+            let list_height = options.len() as f32 * self.style.height;
+            let list_top = if area.bottom + list_height > self.viewport[1] {
+                area.top - list_height
+            } else {
+                area.bottom
+            };
+
+            for (index, option) in options.iter().enumerate() {
+                let top = list_top + index as f32 * self.style.height;
+                let option_area = self.row([position[0], top], width);
+                self.areas.push(option_area);
+
+                let hovered = contains(&option_area, self.cursor);
+                if hovered && self.pressed {
+                    *selected = index;
+                    clicked_option = true;
+                }
+
+                let color = if hovered || index == *selected {
+                    self.style.hovered
+                } else {
+                    self.style.background
+                };
+                self.popup_rects.push(Rect {
+                    position: option_area.center(),
+                    size: option_area.half_size(),
+                    color,
+                    ..Default::default()
+                });
+
+                let text_top =
+                    top + (self.style.height - GLYPH_HEIGHT as f32 * self.style.text_scale) / 2.0;
+                let text = Text::new(
+                    *option,
+                    [position[0] + self.style.padding, text_top],
+                    self.style.text_scale,
+                    self.style.text,
+                );
+                self.popup_rects.extend(text.rects());
+            }
+
+            // Pressing anywhere else closes the list
+            if clicked_option || (self.pressed && !interaction.hovered) {
+                open = false;
+            }
+        }
+
+        if open {
+            self.open_dropdown = Some(id);
+        } else if self.open_dropdown == Some(id) {
+            self.open_dropdown = None;
+        }
+
+        *selected != old
+    }
+}
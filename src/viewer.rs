@@ -0,0 +1,391 @@
+//! `BracketViewer`, the JavaScript API of the web build. wasm-pack generates the TypeScript
+//! definitions from it, including the callback types below.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use winit::event::Event;
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget};
+use winit::platform::web::WindowExtWebSys;
+use winit::window::{Window, WindowId};
+
+use crate::bracket::{MatchId, Tournament};
+use crate::export::{PageLayout, Paper, PdfOptions, Region};
+use crate::state::{State, ViewerEvent};
+use crate::theme::Theme;
+use crate::views::{View, Views};
+use crate::{web, UserEvent};
+
+#[wasm_bindgen(typescript_custom_section)]
+const CALLBACKS: &str = r#"
+/** Round and index in the round of the clicked match, both start at 0 */
+export type MatchClickCallback = (round: number, index: number) => void;
+/** Name of the participant under the cursor, `null` once the cursor left them */
+export type HoverCallback = (participant: string | null) => void;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "MatchClickCallback")]
+    pub type MatchClickCallback;
+
+    #[wasm_bindgen(typescript_type = "HoverCallback")]
+    pub type HoverCallback;
+}
+
+thread_local! {
+    /// Every viewer on the page, they share one event loop
+    static SHARED: Rc<Shared> = Rc::new(Shared::default());
+}
+
+/// What the page attached to a viewer
+#[derive(Default)]
+struct Embedding {
+    /// The canvas was created for the viewer and goes away with it
+    owns_canvas: bool,
+    /// Follows the size of the element around the canvas, only kept to disconnect it when
+    /// the viewer is destroyed
+    _size_observer: Option<web::SizeObserver>,
+    click: Option<js_sys::Function>,
+    hover: Option<js_sys::Function>,
+}
+
+/// A window the running event loop should create, see `UserEvent::CreateWindows`
+struct WindowRequest {
+    element_id: String,
+    window: Rc<RefCell<Option<Result<Window, String>>>>,
+    /// Resolves the promise `create` waits for
+    done: js_sys::Function,
+}
+
+#[derive(Default)]
+struct Shared {
+    views: RefCell<Views>,
+    embeddings: RefCell<HashMap<WindowId, Embedding>>,
+    requests: RefCell<Vec<WindowRequest>>,
+    /// `None` until the first viewer starts the event loop
+    proxy: RefCell<Option<EventLoopProxy<UserEvent>>>,
+    /// Fullscreen and pixel ratio listeners, only while there are viewers
+    page: RefCell<Option<web::PageObservers>>,
+}
+
+impl Shared {
+    async fn create_window(self: &Rc<Self>, element_id: &str) -> Result<Window, String> {
+        let proxy = self.proxy.borrow().clone();
+        let Some(proxy) = proxy else {
+            let event_loop = EventLoop::<UserEvent>::with_user_event();
+            let proxy = event_loop.create_proxy();
+            let window = web::create_window(element_id, &event_loop)?;
+            *self.proxy.borrow_mut() = Some(proxy);
+            self.start(event_loop)?;
+            return Ok(window);
+        };
+
+        // Once it runs, windows can only be created from inside the event loop
+        let window = Rc::new(RefCell::new(None));
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            self.requests.borrow_mut().push(WindowRequest {
+                element_id: element_id.to_string(),
+                window: window.clone(),
+                done: resolve,
+            });
+        });
+        proxy
+            .send_event(UserEvent::CreateWindows)
+            .map_err(|_| "The event loop stopped")?;
+        JsFuture::from(promise)
+            .await
+            .map_err(|_| "The window wasn't created")?;
+
+        let window = window.borrow_mut().take();
+        window.unwrap_or_else(|| Err("The window wasn't created".to_string()))
+    }
+
+    fn start(self: &Rc<Self>, event_loop: EventLoop<UserEvent>) -> Result<(), String> {
+        // `run` never returns and throws to leave Rust, so it starts once `create` resolved
+        let shared = self.clone();
+        let start = Closure::once_into_js::<_, _, ()>(move || {
+            event_loop.run(move |event, target, control_flow| {
+                shared.handle_event(event, target, control_flow);
+            });
+        });
+
+        web_sys::window()
+            .ok_or("No browser window")?
+            .set_timeout_with_callback(start.unchecked_ref())
+            .map_err(|_| "Couldn't start the event loop")?;
+        Ok(())
+    }
+
+    fn handle_event(
+        &self,
+        event: Event<'_, UserEvent>,
+        target: &EventLoopWindowTarget<UserEvent>,
+        control_flow: &mut ControlFlow,
+    ) {
+        if let Event::UserEvent(UserEvent::CreateWindows) = event {
+            self.create_requested_windows(target);
+            return;
+        }
+
+        let events: Vec<(WindowId, ViewerEvent)> = {
+            let mut views = self.views.borrow_mut();
+            views.handle_event(event, control_flow);
+            views
+                .iter_mut()
+                .flat_map(|view| {
+                    let id = view.window.id();
+                    view.state.take_events().into_iter().map(move |e| (id, e))
+                })
+                .collect()
+        };
+
+        // Nothing is borrowed anymore, so the callbacks may use the viewers
+        for (id, event) in events {
+            self.dispatch(id, event);
+        }
+    }
+
+    fn create_requested_windows(&self, target: &EventLoopWindowTarget<UserEvent>) {
+        let requests = std::mem::take(&mut *self.requests.borrow_mut());
+        for request in requests {
+            let window = web::create_window(&request.element_id, target);
+            *request.window.borrow_mut() = Some(window);
+            let _ = request.done.call0(&JsValue::NULL);
+        }
+    }
+
+    fn dispatch(&self, id: WindowId, event: ViewerEvent) {
+        let result = match event {
+            ViewerEvent::MatchClicked(match_id) => {
+                let Some(callback) = self.embedding(id, |e| e.click.clone()) else {
+                    return;
+                };
+                callback.call2(
+                    &JsValue::NULL,
+                    &JsValue::from(match_id.round as u32),
+                    &JsValue::from(match_id.index as u32),
+                )
+            }
+            ViewerEvent::ParticipantHovered(participant) => {
+                let Some(callback) = self.embedding(id, |e| e.hover.clone()) else {
+                    return;
+                };
+
+                let mut views = self.views.borrow_mut();
+                let name = match (participant, views.get_mut(id)) {
+                    (Some(participant), Some(view)) => view
+                        .state
+                        .bracket()
+                        .name(participant)
+                        .map_or(JsValue::NULL, JsValue::from_str),
+                    _ => JsValue::NULL,
+                };
+                drop(views);
+                callback.call1(&JsValue::NULL, &name)
+            }
+        };
+
+        if let Err(err) = result {
+            log::error!("Viewer callback failed: {:?}", err);
+        }
+    }
+
+    fn embedding<T>(&self, id: WindowId, f: impl FnOnce(&Embedding) -> Option<T>) -> Option<T> {
+        self.embeddings.borrow().get(&id).and_then(f)
+    }
+}
+
+/// A tournament bracket drawn into a canvas, panned with the mouse and zoomed with the wheel.
+/// All viewers of a page share one event loop.
+#[wasm_bindgen]
+pub struct BracketViewer {
+    id: WindowId,
+}
+
+#[wasm_bindgen]
+impl BracketViewer {
+    /// Draws into the canvas with id `elementId`, or into a new canvas inside the element with
+    /// that id. The canvas takes the size of its parent element.
+    ///
+    /// Throws if the browser can't draw with WebGL2, the canvas then shows why.
+    pub async fn create(element_id: String) -> Result<BracketViewer, JsValue> {
+        crate::init_logging();
+
+        let shared = SHARED.with(Rc::clone);
+        let window = shared.create_window(&element_id).await.map_err(error)?;
+        let owns_canvas = window.canvas().id() != element_id;
+        // A WebGL context belongs to one canvas, so every viewer has its own device
+        let state = match State::new(&window).await {
+            Ok(state) => state,
+            Err(err) => {
+                crate::show_error(&window, &err);
+                return Err(error(err.to_string()));
+            }
+        };
+
+        let proxy = shared.proxy.borrow().clone();
+        let size_observer = proxy
+            .as_ref()
+            .and_then(|proxy| web::observe_size(&window, proxy.clone()));
+        let listening = shared.page.borrow().is_some();
+        if let (false, Some(proxy)) = (listening, proxy) {
+            match web::observe_page(proxy) {
+                Ok(page) => *shared.page.borrow_mut() = Some(page),
+                Err(err) => log::warn!("Canvases won't follow page changes: {}", err),
+            }
+        }
+
+        let id = window.id();
+        shared.views.borrow_mut().add(View { state, window });
+        shared.embeddings.borrow_mut().insert(
+            id,
+            Embedding {
+                owns_canvas,
+                _size_observer: size_observer,
+                ..Default::default()
+            },
+        );
+
+        Ok(BracketViewer { id })
+    }
+
+    /// Replaces the bracket with a tournament in this format, scores are optional:
+    /// `{ "participants": ["A", "B"], "matches": [{ "round": 0, "index": 0, "scores": [2, 1] }] }`.
+    /// Matches can also have `games` with the score of every game, a `stream` and
+    /// a `scheduled` time.
+    #[wasm_bindgen(js_name = loadTournament)]
+    pub fn load_tournament(&self, json: &str) -> Result<(), JsValue> {
+        let tournament = Tournament::from_json(json).map_err(|err| error(err.to_string()))?;
+        self.with_state(|state| state.set_bracket(tournament.bracket()))
+    }
+
+    /// Switches to a built-in theme, `"dark"`, `"light"` or `"high-contrast"`
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&self, name: &str) -> Result<(), JsValue> {
+        let theme =
+            Theme::named(name).ok_or_else(|| error(format!("Unknown theme \"{}\"", name)))?;
+        self.with_state(|state| state.set_theme(theme))
+    }
+
+    /// Switches to a theme in the JSON format of theme files, missing fields are the ones
+    /// of the dark theme
+    #[wasm_bindgen(js_name = loadTheme)]
+    pub fn load_theme(&self, json: &str) -> Result<(), JsValue> {
+        let theme = Theme::from_json(json).map_err(|err| error(err.to_string()))?;
+        self.with_state(|state| state.set_theme(theme))
+    }
+
+    /// Zooms and pans so the whole bracket is in view
+    #[wasm_bindgen(js_name = fitToView)]
+    pub fn fit_to_view(&self) -> Result<(), JsValue> {
+        self.with_state(State::fit_to_content)
+    }
+
+    /// Centers a match and zooms in on it, returns false if the bracket has no such match
+    #[wasm_bindgen(js_name = zoomToMatch)]
+    pub fn zoom_to_match(&self, round: usize, index: usize) -> Result<bool, JsValue> {
+        self.with_state(|state| state.zoom_to_match(MatchId { round, index }))
+    }
+
+    /// SVG of what is in view, or of everything with `wholeScene`
+    #[wasm_bindgen(js_name = exportSvg)]
+    pub fn export_svg(&self, whole_scene: bool) -> Result<String, JsValue> {
+        let region = if whole_scene {
+            Region::Content
+        } else {
+            Region::View
+        };
+        self.with_state(|state| state.drawing(region).to_svg())
+    }
+
+    /// PDF of the whole scene on `paper` (`a0` to `a4`) in landscape, with `margin`
+    /// millimeters around it. Without `tileScale` it's scaled onto one page, otherwise it's
+    /// printed at that many millimeters per world unit over as many pages as needed.
+    #[wasm_bindgen(js_name = exportPdf)]
+    pub fn export_pdf(
+        &self,
+        paper: &str,
+        margin: f32,
+        tile_scale: Option<f32>,
+    ) -> Result<Vec<u8>, JsValue> {
+        let paper = Paper::from_name(paper)
+            .ok_or_else(|| error(format!("Unknown paper size {}", paper)))?;
+        let options = PdfOptions {
+            paper,
+            landscape: true,
+            margin,
+            layout: match tile_scale {
+                Some(scale) => PageLayout::Tiles { scale },
+                None => PageLayout::Fit,
+            },
+        };
+        self.with_state(|state| state.drawing(Region::Content).to_pdf(&options))
+    }
+
+    /// Called when a match card is clicked, `undefined` removes the callback
+    #[wasm_bindgen(js_name = onMatchClick)]
+    pub fn on_match_click(&self, callback: Option<MatchClickCallback>) {
+        self.with_embedding(|embedding| embedding.click = callback.map(JsCast::unchecked_into));
+    }
+
+    /// Called when the cursor moves onto or off a participant, `undefined` removes the callback
+    #[wasm_bindgen(js_name = onHover)]
+    pub fn on_hover(&self, callback: Option<HoverCallback>) {
+        self.with_embedding(|embedding| embedding.hover = callback.map(JsCast::unchecked_into));
+    }
+
+    /// Stops drawing, frees the GPU resources, stops listening to the page and removes the
+    /// canvas if the viewer created it. The other methods throw afterwards.
+    pub fn destroy(&self) {
+        SHARED.with(|shared| {
+            // Dropping the embedding disconnects its resize observer
+            let embedding = shared.embeddings.borrow_mut().remove(&self.id);
+            let view = shared.views.borrow_mut().remove(self.id);
+
+            if let Some(view) = view {
+                let canvas = view.window.canvas();
+                drop(view);
+                if embedding.is_some_and(|embedding| embedding.owns_canvas) {
+                    canvas.remove();
+                }
+            }
+
+            // The last viewer takes the page listeners with it, the next one adds them again
+            if shared.embeddings.borrow().is_empty() {
+                shared.page.borrow_mut().take();
+            }
+        });
+    }
+}
+
+impl BracketViewer {
+    fn with_state<T>(&self, f: impl FnOnce(&mut State) -> T) -> Result<T, JsValue> {
+        SHARED.with(|shared| {
+            let result = match shared.views.borrow_mut().get_mut(self.id) {
+                Some(view) => f(&mut view.state),
+                None => return Err(error("The viewer was destroyed")),
+            };
+
+            // The event loop sleeps until something happens, this makes it draw the change
+            if let Some(proxy) = shared.proxy.borrow().as_ref() {
+                let _ = proxy.send_event(UserEvent::StateChanged);
+            }
+            Ok(result)
+        })
+    }
+
+    fn with_embedding(&self, f: impl FnOnce(&mut Embedding)) {
+        SHARED.with(|shared| {
+            if let Some(embedding) = shared.embeddings.borrow_mut().get_mut(&self.id) {
+                f(embedding);
+            }
+        });
+    }
+}
+
+fn error(message: impl AsRef<str>) -> JsValue {
+    js_sys::Error::new(message.as_ref()).into()
+}
@@ -0,0 +1,234 @@
+use instant::Instant;
+use std::rc::Rc;
+use std::time::Duration;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::ControlFlow;
+use winit::window::{Window, WindowId};
+
+use crate::gpu::{Gpu, InitError};
+use crate::state::State;
+use crate::UserEvent;
+
+/// A window and the state drawn into it
+pub struct View {
+    // Dropped before the window, the surface belongs to it
+    pub state: State,
+    pub window: Window,
+}
+
+/// How long to wait before recreating a lost device again, e.g. while the driver resets
+const RECOVERY_RETRY: Duration = Duration::from_secs(1);
+
+/// Windows sharing one event loop, every event goes to the window it belongs to
+#[derive(Default)]
+pub struct Views {
+    views: Vec<View>,
+    /// A lost device couldn't be recreated yet, the next try is due then
+    recovery_retry: Option<Instant>,
+}
+
+impl Views {
+    pub fn add(&mut self, view: View) {
+        self.views.push(view);
+    }
+
+    pub fn remove(&mut self, id: WindowId) -> Option<View> {
+        let index = self.views.iter().position(|view| view.window.id() == id)?;
+        Some(self.views.remove(index))
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut View> {
+        self.views.iter_mut().find(|view| view.window.id() == id)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut View> {
+        self.views.iter_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Closing a window removes its view, `control_flow` only exits when nothing can be drawn
+    pub fn handle_event(&mut self, event: Event<'_, UserEvent>, control_flow: &mut ControlFlow) {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } => {
+                let Some(view) = self.get_mut(window_id) else {
+                    return;
+                };
+                if view.state.input(event) {
+                    return;
+                }
+
+                match event {
+                    WindowEvent::Resized(physical_size) => {
+                        view.state.resize(*physical_size);
+                    }
+
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        view.state.set_scale_factor(*scale_factor);
+                        // new_inner_size is &&mut so we have to dereference it twice
+                        view.state.resize(**new_inner_size);
+                    }
+
+                    WindowEvent::CloseRequested => {
+                        self.remove(window_id);
+                    }
+
+                    // A page can't close its canvas, so only native windows close on Escape
+                    #[cfg(not(target_arch = "wasm32"))]
+                    WindowEvent::KeyboardInput {
+                        input:
+                            winit::event::KeyboardInput {
+                                state: winit::event::ElementState::Pressed,
+                                virtual_keycode: Some(winit::event::VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => {
+                        self.remove(window_id);
+                    }
+                    _ => {}
+                }
+            }
+
+            Event::RedrawRequested(window_id) => {
+                let Some(view) = self.get_mut(window_id) else {
+                    return;
+                };
+
+                if !view.state.gpu().is_lost() {
+                    view.state.update();
+                    match view.state.render() {
+                        Ok(_) => {}
+                        // Reconfigure the surface if lost, or recreate the device if that
+                        // didn't help
+                        Err(wgpu::SurfaceError::Lost) => view.state.surface_lost(),
+                        // Nothing fits into the old device anymore, start over with a new one
+                        Err(wgpu::SurfaceError::OutOfMemory) => {
+                            view.state.gpu().mark_surface_failed();
+                            view.state.gpu().mark_lost();
+                        }
+                        // All other errors (Outdated, Timeout) should be resolved by the next
+                        // frame
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            view.state.request_redraw();
+                        }
+                    }
+                }
+
+                // The device is recreated once the loop comes around again, which it
+                // wouldn't without other events after `MainEventsCleared` chose to wait.
+                // Nobody may be there to move the mouse, e.g. at a venue display.
+                let lost = view.state.gpu().is_lost();
+                if lost && self.recovery_retry.is_none() {
+                    *control_flow = ControlFlow::Poll;
+                }
+            }
+
+            // Whoever changed the state marked it dirty, the redraw is scheduled below
+            Event::UserEvent(UserEvent::StateChanged) => {}
+
+            #[cfg(target_arch = "wasm32")]
+            Event::UserEvent(UserEvent::CanvasResized(window_id)) => {
+                if let Some(view) = self.get_mut(window_id) {
+                    crate::web::fit_canvas(&view.window);
+                }
+            }
+
+            // Both change the size of every canvas on the page
+            #[cfg(target_arch = "wasm32")]
+            Event::UserEvent(UserEvent::FullscreenChanged | UserEvent::PixelRatioChanged) => {
+                for view in &self.views {
+                    crate::web::fit_canvas(&view.window);
+                }
+            }
+
+            // Only draw what changed, otherwise sleep until the next event
+            // or the next animation frame
+            Event::MainEventsCleared if *control_flow != ControlFlow::Exit => {
+                self.recover_lost_devices();
+
+                let mut next = self.recovery_retry;
+                for view in &mut self.views {
+                    view.state.apply_fullscreen(&view.window);
+                    match view.state.next_redraw() {
+                        Some(at) if at <= Instant::now() => view.window.request_redraw(),
+                        Some(at) => next = Some(next.map_or(at, |next: Instant| next.min(at))),
+                        None => {}
+                    }
+                }
+
+                *control_flow = match next {
+                    Some(at) => ControlFlow::WaitUntil(at),
+                    None => ControlFlow::Wait,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Recreates every lost device with the states drawn with it. Windows that shared a device
+    /// share the new one too, and either all of them move to it or none does. If that fails
+    /// it's tried again after `RECOVERY_RETRY`.
+    fn recover_lost_devices(&mut self) {
+        if self.recovery_retry.is_some_and(|at| at > Instant::now()) {
+            return;
+        }
+        self.recovery_retry = None;
+
+        while let Some(lost) = self.lost_gpu() {
+            log::warn!("Recreating the lost graphics device");
+            let fresh = match self.replacements(&lost) {
+                Ok(fresh) => fresh,
+                Err(err) => {
+                    log::error!("Couldn't recreate the graphics device: {}", err);
+                    self.recovery_retry = Some(Instant::now() + RECOVERY_RETRY);
+                    return;
+                }
+            };
+
+            let affected = self
+                .views
+                .iter_mut()
+                .filter(|view| Rc::ptr_eq(view.state.gpu(), &lost));
+            for (view, fresh) in affected.zip(fresh) {
+                view.state.recover(fresh);
+            }
+        }
+    }
+
+    /// New states for every window drawn with `lost`, in order, all on one new device
+    fn replacements(&self, lost: &Rc<Gpu>) -> Result<Vec<State>, InitError> {
+        let mut fresh: Vec<State> = Vec::new();
+        for view in &self.views {
+            if !Rc::ptr_eq(view.state.gpu(), lost) {
+                continue;
+            }
+
+            let state = match fresh.first() {
+                Some(first) => State::with_gpu(&view.window, Rc::clone(first.gpu()))?,
+                // wgpu-core resolves these futures right away, so this never blocks
+                None => pollster::block_on(State::new(&view.window))?,
+            };
+            fresh.push(state);
+        }
+
+        Ok(fresh)
+    }
+
+    fn lost_gpu(&self) -> Option<Rc<Gpu>> {
+        self.views
+            .iter()
+            .map(|view| view.state.gpu())
+            .find(|gpu| gpu.is_lost())
+            .cloned()
+    }
+}
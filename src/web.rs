@@ -0,0 +1,231 @@
+//! Browser glue, the canvas follows the size of its parent element and the device pixel ratio
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::{prelude::*, JsCast};
+use winit::dpi::PhysicalSize;
+use winit::event_loop::{EventLoopProxy, EventLoopWindowTarget};
+use winit::platform::web::{WindowBuilderExtWebSys, WindowExtWebSys};
+use winit::window::{Window, WindowBuilder};
+
+use crate::UserEvent;
+
+// web-sys only has it behind `web_sys_unstable_apis` in the versions wgpu allows
+#[wasm_bindgen]
+extern "C" {
+    type ResizeObserver;
+
+    #[wasm_bindgen(constructor, catch)]
+    fn new(callback: &JsValue) -> Result<ResizeObserver, JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn observe(this: &ResizeObserver, target: &web_sys::Element);
+
+    #[wasm_bindgen(method)]
+    fn disconnect(this: &ResizeObserver);
+}
+
+fn browser_window() -> web_sys::Window {
+    web_sys::window().expect("No browser window")
+}
+
+/// Creates a window on the canvas with id `element_id`, or on a new canvas appended to the
+/// element with that id, sized to fit its parent. `observe_size` and `observe_page` keep it
+/// fitting.
+pub fn create_window(
+    element_id: &str,
+    event_loop: &EventLoopWindowTarget<UserEvent>,
+) -> Result<Window, String> {
+    let document = browser_window().document().ok_or("No document")?;
+    let element = document
+        .get_element_by_id(element_id)
+        .ok_or_else(|| format!("No element with id \"{}\"", element_id))?;
+
+    let window = match element.dyn_into::<web_sys::HtmlCanvasElement>() {
+        Ok(canvas) => WindowBuilder::new()
+            .with_canvas(Some(canvas))
+            .build(event_loop)
+            .map_err(|err| err.to_string())?,
+        Err(element) => {
+            let window = WindowBuilder::new()
+                .build(event_loop)
+                .map_err(|err| err.to_string())?;
+            element
+                .append_child(&window.canvas())
+                .map_err(|_| "Couldn't append the canvas")?;
+            window
+        }
+    };
+
+    // Winit prevents sizing with CSS, so the canvas follows its parent element by hand
+    fit_canvas(&window);
+
+    Ok(window)
+}
+
+/// Resizes the canvas to `canvas_size`, which sends a `Resized` event if the size changed
+pub fn fit_canvas(window: &Window) {
+    if let Some(size) = canvas_size(&window.canvas()) {
+        window.set_inner_size(size);
+    }
+}
+
+/// Hides the canvas behind `message`, for when nothing can be drawn into it
+pub fn show_error(window: &Window, message: &str) {
+    let canvas = window.canvas();
+    let (Some(document), Some(parent)) = (browser_window().document(), canvas.parent_node()) else {
+        return;
+    };
+    let Ok(text) = document.create_element("p") else {
+        return;
+    };
+
+    text.set_class_name("bracket-viewer-error");
+    text.set_text_content(Some(message));
+    let _ = text.set_attribute(
+        "style",
+        "margin: 0; padding: 1em; font-family: sans-serif; color: silver; background: #191959;",
+    );
+    let _ = parent.insert_before(&text, Some(&canvas));
+    let _ = canvas.set_attribute("style", "display: none;");
+}
+
+/// Size the canvas should have in physical pixels, the whole page while fullscreen
+/// and its parent element otherwise
+pub fn canvas_size(canvas: &web_sys::Element) -> Option<PhysicalSize<u32>> {
+    let window = browser_window();
+    let fullscreen = window
+        .document()
+        .and_then(|doc| doc.fullscreen_element())
+        .is_some();
+
+    let (width, height) = if fullscreen {
+        (
+            window.inner_width().ok()?.as_f64()?,
+            window.inner_height().ok()?.as_f64()?,
+        )
+    } else {
+        let parent = canvas.parent_element()?;
+        (parent.client_width() as f64, parent.client_height() as f64)
+    };
+
+    let ratio = window.device_pixel_ratio();
+    Some(PhysicalSize::new(
+        (width * ratio).round() as u32,
+        (height * ratio).round() as u32,
+    ))
+}
+
+/// Watches the parent element of a canvas, disconnects when dropped
+pub struct SizeObserver {
+    observer: ResizeObserver,
+    _on_resize: Closure<dyn FnMut()>,
+}
+
+impl Drop for SizeObserver {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+/// Sends `CanvasResized` for `window` every time the parent element of its canvas changes
+/// size, `None` if the canvas has no parent
+pub fn observe_size(window: &Window, proxy: EventLoopProxy<UserEvent>) -> Option<SizeObserver> {
+    let parent = window.canvas().parent_element()?;
+    let window_id = window.id();
+    let on_resize = Closure::wrap(Box::new(move || {
+        let _ = proxy.send_event(UserEvent::CanvasResized(window_id));
+    }) as Box<dyn FnMut()>);
+
+    let observer =
+        ResizeObserver::new(on_resize.as_ref()).expect("Couldn't create a ResizeObserver.");
+    observer.observe(&parent);
+
+    Some(SizeObserver {
+        observer,
+        _on_resize: on_resize,
+    })
+}
+
+/// Listeners for changes that resize every canvas on the page, one set per page no matter
+/// how many viewers it has. They stop when dropped.
+pub struct PageObservers {
+    document: web_sys::Document,
+    on_fullscreen_change: Closure<dyn FnMut()>,
+    /// The media query that matches the current pixel ratio
+    pixel_ratio: Rc<RefCell<Option<web_sys::MediaQueryList>>>,
+    _on_pixel_ratio_change: Closure<dyn FnMut()>,
+}
+
+impl Drop for PageObservers {
+    fn drop(&mut self) {
+        let _ = self.document.remove_event_listener_with_callback(
+            "fullscreenchange",
+            self.on_fullscreen_change.as_ref().unchecked_ref(),
+        );
+        // Before the closure goes, so the browser can't call it anymore
+        if let Some(list) = self.pixel_ratio.borrow_mut().take() {
+            list.set_onchange(None);
+        }
+    }
+}
+
+/// Sends `FullscreenChanged` when the page enters or leaves fullscreen and
+/// `PixelRatioChanged` when the device pixel ratio changes
+pub fn observe_page(proxy: EventLoopProxy<UserEvent>) -> Result<PageObservers, String> {
+    let document = browser_window().document().ok_or("No document")?;
+
+    // Winit only sends the canvas into fullscreen, it keeps its size unless we change it
+    let fullscreen_proxy = proxy.clone();
+    let on_fullscreen_change = Closure::wrap(Box::new(move || {
+        let _ = fullscreen_proxy.send_event(UserEvent::FullscreenChanged);
+    }) as Box<dyn FnMut()>);
+    document
+        .add_event_listener_with_callback(
+            "fullscreenchange",
+            on_fullscreen_change.as_ref().unchecked_ref(),
+        )
+        .map_err(|_| "Couldn't listen for fullscreen changes")?;
+
+    let pixel_ratio: Rc<RefCell<Option<web_sys::MediaQueryList>>> = Rc::new(RefCell::new(None));
+    let current = pixel_ratio.clone();
+    let on_pixel_ratio_change = Closure::wrap(Box::new(move || {
+        let Some(list) = current.borrow_mut().take() else {
+            return;
+        };
+        // The closure can't name itself, the list it was set on hands it over
+        let Some(on_change) = list.onchange() else {
+            return;
+        };
+        list.set_onchange(None);
+        let _ = proxy.send_event(UserEvent::PixelRatioChanged);
+        observe_pixel_ratio(&current, &on_change);
+    }) as Box<dyn FnMut()>);
+    observe_pixel_ratio(&pixel_ratio, on_pixel_ratio_change.as_ref().unchecked_ref());
+
+    Ok(PageObservers {
+        document,
+        on_fullscreen_change,
+        pixel_ratio,
+        _on_pixel_ratio_change: on_pixel_ratio_change,
+    })
+}
+
+/// Calls `on_change` on the next change of the device pixel ratio, when the browser zooms or
+/// the window moves to another monitor. `current` holds the media query that waits, so it
+/// can be stopped.
+fn observe_pixel_ratio(
+    current: &RefCell<Option<web_sys::MediaQueryList>>,
+    on_change: &js_sys::Function,
+) {
+    let window = browser_window();
+    // A media query only matches a single ratio, so it's replaced after every change
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let list = match window.match_media(&query) {
+        Ok(Some(list)) => list,
+        _ => return,
+    };
+
+    list.set_onchange(Some(on_change));
+    *current.borrow_mut() = Some(list);
+}